@@ -1,4 +1,11 @@
-use crate::util::{alpha_bleed::alpha_bleed, svg::svg_to_png};
+use crate::{
+    pack::rect::Rect,
+    util::{
+        alpha_bleed::alpha_bleed,
+        aseprite, psd, slice,
+        svg::{SvgRaster, svg_to_png},
+    },
+};
 use anyhow::{Context, bail};
 use blake3::Hasher;
 use bytes::Bytes;
@@ -17,11 +24,17 @@ pub struct Asset {
     pub ext: String,
     /// The hash before processing
     pub hash: String,
+    /// The nine-slice center, for images scaled with `ScaleType.Slice`
+    pub slice_center: Option<Rect>,
+    /// The size an SVG is rasterized at when processed
+    pub svg_raster: SvgRaster,
 }
 
 impl Asset {
-    pub fn new(path: RelativePathBuf, data: Vec<u8>) -> anyhow::Result<Self> {
-        let ext = path
+    /// Aseprite files are flattened into their first frame and PSDs into
+    /// their composite image here; syncing splits them up beforehand
+    pub fn new(path: RelativePathBuf, mut data: Vec<u8>) -> anyhow::Result<Self> {
+        let mut ext = path
             .extension()
             .context("File has no extension")?
             .to_string();
@@ -31,7 +44,7 @@ impl Asset {
             "ogg" => AssetType::Audio(AudioType::Ogg),
             "flac" => AssetType::Audio(AudioType::Flac),
             "wav" => AssetType::Audio(AudioType::Wav),
-            "png" | "svg" => AssetType::Image(ImageType::Png),
+            "png" | "svg" | "aseprite" | "ase" | "psd" => AssetType::Image(ImageType::Png),
             "jpg" | "jpeg" => AssetType::Image(ImageType::Jpg),
             "bmp" => AssetType::Image(ImageType::Bmp),
             "tga" => AssetType::Image(ImageType::Tga),
@@ -56,22 +69,72 @@ impl Asset {
             _ => bail!("Unknown extension .{ext}"),
         };
 
-        let data = Bytes::from(data);
-
         let mut hasher = Hasher::new();
         hasher.update(&data);
         let hash = hasher.finalize().to_string();
 
+        if ext == "aseprite" || ext == "ase" {
+            let mut decoded = aseprite::decode(&data)?;
+            if decoded.frames.is_empty() {
+                bail!("Aseprite file has no frames");
+            }
+
+            data = decoded.frames.swap_remove(0).data;
+            ext = "png".to_string();
+        }
+
+        if ext == "psd" {
+            data = psd::flatten(&data)?;
+            ext = "png".to_string();
+        }
+
+        // Nine-patch images are known by their name without the `.9`, with
+        // their marker border stripped off
+        let mut path = path;
+        let mut slice_center = None;
+        if let Some(nine_patch_path) = slice::nine_patch_path(&path) {
+            let (stripped, center) = slice::strip_nine_patch(&data)
+                .with_context(|| format!("Failed to read nine-patch image {path}"))?;
+
+            path = nine_patch_path;
+            data = stripped;
+            slice_center = Some(center);
+        }
+
         Ok(Self {
             path,
-            data,
+            data: Bytes::from(data),
             ty,
             processed: false,
             ext,
             hash,
+            slice_center,
+            svg_raster: SvgRaster::default(),
         })
     }
 
+    /// Set the nine-slice center, which is part of the hash so changing it
+    /// syncs the image again
+    pub fn set_slice_center(&mut self, center: Rect) {
+        let mut hasher = Hasher::new();
+        hasher.update(self.hash.as_bytes());
+        hasher.update(format!("{center:?}").as_bytes());
+        self.hash = hasher.finalize().to_string();
+
+        self.slice_center = Some(center);
+    }
+
+    /// Set the size an SVG is rasterized at, which is part of the hash so
+    /// changing it uploads the image again
+    pub fn set_svg_raster(&mut self, raster: SvgRaster) {
+        let mut hasher = Hasher::new();
+        hasher.update(self.hash.as_bytes());
+        hasher.update(format!("{raster:?}").as_bytes());
+        self.hash = hasher.finalize().to_string();
+
+        self.svg_raster = raster;
+    }
+
     pub async fn process(
         &mut self,
         font_db: Arc<Database>,
@@ -83,7 +146,9 @@ impl Asset {
         }
 
         if self.ext == "svg" {
-            self.data = svg_to_png(&self.data, font_db.clone()).await?.into();
+            self.data = svg_to_png(&self.data, font_db.clone(), self.svg_raster)
+                .await?
+                .into();
             self.ext = "png".to_string();
         }
 
@@ -1,7 +1,11 @@
-use crate::config::{CreatorType, PackAlgorithm, PackSort};
+use crate::config::{
+    CreatorType, PackAlgorithm, PackFreeRectChoice, PackHeuristic, PackOverflow, PackSort,
+    PackSplitRule,
+};
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 use clap_verbosity_flag::{InfoLevel, Verbosity};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(version, about = "Upload and reference Roblox assets in code.")]
@@ -18,6 +22,17 @@ pub enum Commands {
     /// Sync assets.
     Sync(SyncArgs),
 
+    /// Repack every sprite of an input from scratch and upload the consolidated atlas pages.
+    ///
+    /// Regular syncs only pack sprites that are new or changed, so atlases slowly fill up with half-empty pages.
+    /// This ignores the sprite entries in the lockfile, packs everything again and replaces them once every page has been uploaded.
+    Repack(RepackArgs),
+
+    /// Pack every input's sprites and write the atlas pages with an HTML report, without syncing.
+    ///
+    /// Each page is also rendered with its sprites outlined and named and their padding shaded, so you can tune packing options before uploading anything.
+    PackPreview(PackPreviewArgs),
+
     /// Uploads a single asset and returns the asset ID.
     Upload(UploadArgs),
 
@@ -68,6 +83,10 @@ pub struct SyncArgs {
     #[arg(long)]
     pub expected_price: Option<u32>,
 
+    /// Maximum number of assets to upload at once, overriding the config's concurrency.
+    #[arg(long)]
+    pub concurrency: Option<usize>,
+
     // Pack-related arguments
     /// Enable packing for all inputs that support it.
     #[arg(long)]
@@ -93,6 +112,18 @@ pub struct SyncArgs {
     #[arg(long)]
     pub pack_algorithm: Option<PackAlgorithm>,
 
+    /// MaxRects placement heuristic to use.
+    #[arg(long)]
+    pub pack_heuristic: Option<PackHeuristic>,
+
+    /// Guillotine rule for choosing the free space a sprite goes into.
+    #[arg(long)]
+    pub pack_free_rect_choice: Option<PackFreeRectChoice>,
+
+    /// Guillotine rule for splitting the free space left around a sprite.
+    #[arg(long)]
+    pub pack_split_rule: Option<PackSplitRule>,
+
     /// Enable sprite trimming to remove transparent borders.
     #[arg(long)]
     pub pack_trim: bool,
@@ -101,10 +132,18 @@ pub struct SyncArgs {
     #[arg(long)]
     pub pack_no_trim: bool,
 
+    /// Keep every atlas page at the maximum size instead of shrinking it to fit.
+    #[arg(long)]
+    pub pack_no_shrink: bool,
+
     /// Maximum number of atlas pages to generate.
     #[arg(long)]
     pub pack_page_limit: Option<u32>,
 
+    /// What to do with sprites that are too large or exceed the page limit.
+    #[arg(long)]
+    pub pack_overflow: Option<PackOverflow>,
+
     /// Sprite sorting method for deterministic packing.
     #[arg(long)]
     pub pack_sort: Option<PackSort>,
@@ -113,11 +152,45 @@ pub struct SyncArgs {
     #[arg(long)]
     pub pack_dedupe: bool,
 
+    /// Allow sprites to be rotated by 90 degrees in the atlas.
+    #[arg(long)]
+    pub pack_rotate: bool,
+
+    /// Disable sprite rotation.
+    #[arg(long)]
+    pub pack_no_rotate: bool,
+
+    /// Keep unchanged sprites in place and only re-upload the atlas pages that changed.
+    #[arg(long)]
+    pub pack_incremental: bool,
+
     /// Optimize PNG assets with oxipng for smaller file sizes.
     #[arg(long)]
     pub optimize: bool,
 }
 
+#[derive(Args, Clone)]
+pub struct RepackArgs {
+    /// The input to repack, along with any inputs sharing its atlas. Every input with packing enabled is repacked if omitted.
+    pub input: Option<String>,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+#[derive(Args, Clone)]
+pub struct PackPreviewArgs {
+    /// The input to preview. Every input with packing enabled is previewed if omitted.
+    pub input: Option<String>,
+
+    /// The directory to write the preview to.
+    #[arg(short, long, default_value = ".asphalt-preview")]
+    pub output: PathBuf,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
 fn parse_size(s: &str) -> Result<(u32, u32), String> {
     let parts: Vec<&str> = s.split('x').collect();
     if parts.len() != 2 {
@@ -1,11 +1,14 @@
 use crate::glob::Glob;
-use anyhow::Context;
+use anyhow::{Context, bail};
 use clap::ValueEnum;
 use fs_err::tokio as fs;
 use relative_path::RelativePathBuf;
 use schemars::JsonSchema;
 use serde::Deserialize;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
 
 #[derive(Debug, Deserialize, Clone, JsonSchema)]
 #[schemars(description = "Asphalt configuration file")]
@@ -19,6 +22,24 @@ pub struct Config {
 
     #[schemars(description = "Asset input configurations mapped by name")]
     pub inputs: HashMap<String, Input>,
+
+    #[serde(default)]
+    #[schemars(
+        description = "Atlases shared by several inputs mapped by name, each with its own packing configuration. Inputs opt in with their atlas setting"
+    )]
+    pub atlases: BTreeMap<String, PackOptions>,
+
+    #[serde(default = "default_concurrency")]
+    #[schemars(description = "Maximum number of assets to upload at once (default: 4)")]
+    pub concurrency: usize,
+
+    #[serde(default)]
+    #[schemars(description = "How failed requests to Roblox are retried")]
+    pub retry: RetryOptions,
+
+    #[serde(default)]
+    #[schemars(description = "How long to wait on Roblox to process uploads")]
+    pub polling: PollOptions,
 }
 
 pub const CONFIG_FILES: &[&str] = &[
@@ -37,7 +58,7 @@ impl Config {
                     .await
                     .with_context(|| format!("Failed to read config file: {}", file_name))?;
 
-                let config = match file_name {
+                let config: Config = match file_name {
                     name if name.ends_with(".json") => {
                         // Use fjson for lenient JSON parsing (supports trailing commas and comments)
                         let clean_json = fjson::to_json(&content).with_context(|| {
@@ -74,6 +95,8 @@ impl Config {
                     }
                 };
 
+                config.validate()?;
+
                 log::info!("Loaded configuration from {}", file_name);
                 return Ok(config);
             }
@@ -84,6 +107,139 @@ impl Config {
             CONFIG_FILES.join(", ")
         ))
     }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.concurrency == 0 {
+            bail!("Concurrency must be at least 1");
+        }
+
+        if self.retry.max_attempts == 0 {
+            bail!("Retries need max_attempts of at least 1");
+        }
+
+        if self.retry.timeout == 0 {
+            bail!("Retries need a timeout of at least 1 second");
+        }
+
+        if self.polling.max_polls == 0 {
+            bail!("Polling needs max_polls of at least 1");
+        }
+
+        if !(self.polling.interval.is_finite() && self.polling.interval > 0.0) {
+            bail!("Polling needs an interval greater than zero");
+        }
+
+        for (input_name, input) in &self.inputs {
+            input
+                .svg
+                .validate()
+                .with_context(|| format!("Invalid svg options for input '{input_name}'"))?;
+
+            for theme in input.themes.keys() {
+                // Themes are folders in the input
+                if theme.is_empty() || theme == "." || theme == ".." || theme.contains(['/', '\\'])
+                {
+                    bail!("Input '{input_name}' has a theme with an invalid name: '{theme}'");
+                }
+            }
+
+            // The variants module is written next to the input's generated code
+            if self.codegen.variant_helper
+                && input.variants
+                && self
+                    .inputs
+                    .get("variants")
+                    .is_some_and(|other| other.output_path == input.output_path)
+            {
+                bail!(
+                    "Input '{input_name}' writes a variants module to {}, where the input 'variants' writes its generated code",
+                    input.output_path.display()
+                );
+            }
+
+            let Some(atlas) = &input.atlas else {
+                continue;
+            };
+
+            if !self.atlases.contains_key(atlas) {
+                bail!("Input '{input_name}' uses the atlas '{atlas}', which isn't in atlases");
+            }
+
+            if input.pack.is_some() {
+                log::warn!(
+                    "Input '{input_name}' packs into the shared atlas '{atlas}', so its own pack options are ignored"
+                );
+            }
+        }
+
+        for (atlas_name, atlas) in &self.atlases {
+            // Atlas pages are synced under the atlas' name, just like an input's
+            if self.inputs.contains_key(atlas_name) {
+                bail!("The atlas '{atlas_name}' has the same name as an input");
+            }
+
+            if atlas.write_atlases && atlas.atlas_dir.is_none() {
+                bail!(
+                    "The atlas '{atlas_name}' writes its atlases to disk, so it needs an atlas_dir"
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+#[schemars(description = "Retry configuration")]
+pub struct RetryOptions {
+    #[schemars(
+        description = "Attempts made at a request before giving up, when it's rate limited, hits a server error or times out (default: 5)"
+    )]
+    pub max_attempts: u32,
+    #[schemars(description = "Seconds each attempt may take before it times out (default: 120)")]
+    pub timeout: u64,
+}
+
+impl Default for RetryOptions {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            timeout: 120,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+#[schemars(description = "Upload operation polling configuration")]
+pub struct PollOptions {
+    #[schemars(
+        description = "Times to check whether Roblox has processed an upload before leaving it for the next sync (default: 10)"
+    )]
+    pub max_polls: u32,
+    #[schemars(
+        description = "Seconds to wait before checking an upload again, doubling after each check up to 30 seconds (default: 1)"
+    )]
+    pub interval: f64,
+    #[schemars(
+        description = "Seconds to wait on an upload in total before leaving it for the next sync (default: 300)"
+    )]
+    pub timeout: u64,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            max_polls: 10,
+            interval: 1.0,
+            timeout: 300,
+        }
+    }
+}
+
+fn default_concurrency() -> usize {
+    4
 }
 
 fn default_input_naming_convention() -> InputNamingConvention {
@@ -116,6 +272,10 @@ pub struct Codegen {
         description = "Naming convention for asset keys in generated code (default: preserve)"
     )]
     pub asset_naming_convention: AssetNamingConvention,
+    #[schemars(
+        description = "Write a variants module next to the generated code of inputs with resolution variants, with a helper that picks the variant for the viewport size"
+    )]
+    pub variant_helper: bool,
 }
 
 /// The type of Creator
@@ -154,6 +314,10 @@ pub struct Input {
     pub output_path: PathBuf,
     #[schemars(description = "Sprite packing/atlas generation configuration (optional)")]
     pub pack: Option<PackOptions>,
+    #[schemars(
+        description = "Name of a shared atlas from the atlases section to pack this input's images into, instead of its own (optional)"
+    )]
+    pub atlas: Option<String>,
 
     /// Enable alpha bleeding images. Keep in mind that changing this setting won't invalidate your lockfile or reupload your images
     #[serde(default = "default_true")]
@@ -171,6 +335,140 @@ pub struct Input {
     #[serde(default = "default_true")]
     #[schemars(description = "Warn for each duplicate file found (default: true)")]
     pub warn_each_duplicate: bool,
+
+    /// Upload images that have a TexturePacker or Aseprite JSON data file next to them as finished spritesheets
+    #[serde(default)]
+    #[schemars(
+        description = "Treat images with a TexturePacker or Aseprite .json data file of the same name next to them as finished spritesheets, uploaded as they are with a sprite for each frame (default: false)"
+    )]
+    pub spritesheets: bool,
+
+    /// Group resolution variants such as `icon@2x.png` with the image they're a variant of
+    #[serde(default)]
+    #[schemars(
+        description = "Group resolution variants such as icon@2x.png with icon.png into one codegen entry listing every variant and its scale (default: false)"
+    )]
+    pub variants: bool,
+
+    /// How PSD files are split into an image per layer
+    #[serde(default)]
+    #[schemars(description = "How PSD files are split into an image per layer (optional)")]
+    pub psd: PsdOptions,
+
+    /// The size SVG files are rasterized at
+    #[serde(default)]
+    #[schemars(
+        description = "The size SVG files are rasterized at, which a .raster.json sidecar next to a file overrides (default: their intrinsic size)"
+    )]
+    pub svg: SvgOptions,
+
+    /// Themes SVG files are rendered in, each with its own set of colors
+    #[serde(default)]
+    #[schemars(
+        description = "Themes to render SVG files in, each recoloring them and placing them under a folder named after the theme (optional)"
+    )]
+    pub themes: BTreeMap<String, SvgTheme>,
+
+    /// How changed files are uploaded
+    #[serde(default)]
+    #[schemars(
+        description = "Whether a changed file is uploaded as a new asset or as a new version of the asset it was uploaded as before (default: new_asset)"
+    )]
+    pub update_strategy: UpdateStrategy,
+
+    /// Whether a new version Roblox rejects is uploaded as a new asset
+    #[serde(default = "default_true")]
+    #[schemars(
+        description = "Upload a changed file as a new asset when Roblox rejects it as a new version of its previous one, instead of failing (default: true)"
+    )]
+    pub update_fallback: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "PSD import configuration")]
+pub struct PsdOptions {
+    #[schemars(
+        description = "Regex matched against the names of top-level groups, each matching group is flattened into one image and the rest are left out (default: every group is flattened)"
+    )]
+    pub group_pattern: Option<String>,
+    #[schemars(
+        description = "Pack the layers as sprites when the input is packed, rather than uploading them individually (default: true)"
+    )]
+    pub pack: bool,
+}
+
+impl Default for PsdOptions {
+    fn default() -> Self {
+        Self {
+            group_pattern: None,
+            pack: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+#[schemars(description = "SVG rasterization configuration")]
+pub struct SvgOptions {
+    #[schemars(
+        description = "Width to render at in pixels, keeping the aspect ratio unless a height is also set"
+    )]
+    pub width: Option<u32>,
+    #[schemars(
+        description = "Height to render at in pixels, keeping the aspect ratio unless a width is also set"
+    )]
+    pub height: Option<u32>,
+    #[schemars(
+        description = "Factor to scale the size by, after the width and height are applied"
+    )]
+    pub scale: Option<f32>,
+    #[schemars(
+        description = "Widths to render at, each as its own image: the smallest is named after the file and the rest as resolution variants of it, e.g. icon@2x.svg"
+    )]
+    pub sizes: Vec<u32>,
+    #[schemars(
+        description = "DPI to convert lengths in physical units such as mm or pt to pixels with (default: 96)"
+    )]
+    pub dpi: Option<f32>,
+}
+
+impl SvgOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.width == Some(0) || self.height == Some(0) || self.sizes.contains(&0) {
+            bail!("SVG sizes must be greater than zero");
+        }
+
+        if let Some(scale) = self.scale
+            && !(scale.is_finite() && scale > 0.0)
+        {
+            bail!("SVG scale must be greater than zero");
+        }
+
+        if let Some(dpi) = self.dpi
+            && !(dpi.is_finite() && dpi > 0.0)
+        {
+            bail!("SVG dpi must be greater than zero");
+        }
+
+        if !self.sizes.is_empty()
+            && (self.width.is_some() || self.height.is_some() || self.scale.is_some())
+        {
+            bail!("SVG sizes can't be combined with a width, height or scale");
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(description = "SVG theme configuration")]
+pub struct SvgTheme {
+    #[schemars(
+        description = "Map of colors to the colors they're replaced with, such as currentColor or #000000 to #ffffff"
+    )]
+    pub colors: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Deserialize, Clone, JsonSchema)]
@@ -200,10 +498,30 @@ fn default_pack_algorithm() -> PackAlgorithm {
     PackAlgorithm::MaxRects
 }
 
+fn default_pack_heuristic() -> PackHeuristic {
+    PackHeuristic::BestAreaFit
+}
+
+fn default_pack_free_rect_choice() -> PackFreeRectChoice {
+    PackFreeRectChoice::BestShortSideFit
+}
+
+fn default_pack_split_rule() -> PackSplitRule {
+    PackSplitRule::ShorterLeftoverAxis
+}
+
+fn default_pack_overflow() -> PackOverflow {
+    PackOverflow::Error
+}
+
 fn default_pack_sort() -> PackSort {
     PackSort::Area
 }
 
+fn default_sequence_pattern() -> String {
+    r"^(?P<name>.+)[_-](?P<frame>\d+)$".to_string()
+}
+
 #[derive(Debug, Deserialize, Clone, JsonSchema)]
 #[serde(default)]
 #[schemars(description = "Sprite packing configuration")]
@@ -218,6 +536,11 @@ pub struct PackOptions {
     #[serde(default = "default_pack_power_of_two")]
     #[schemars(description = "Constrain atlas dimensions to power-of-two sizes (default: true)")]
     pub power_of_two: bool,
+    #[serde(default = "default_true")]
+    #[schemars(
+        description = "Shrink each atlas page to the smallest size that fits its sprites (default: true)"
+    )]
+    pub shrink_to_fit: bool,
     #[serde(default = "default_pack_padding")]
     #[schemars(description = "Padding between sprites in pixels (default: 2)")]
     pub padding: u32,
@@ -231,15 +554,64 @@ pub struct PackOptions {
     #[serde(default = "default_pack_algorithm")]
     #[schemars(description = "Packing algorithm to use (default: max_rects)")]
     pub algorithm: PackAlgorithm,
+    #[serde(default = "default_pack_heuristic")]
+    #[schemars(
+        description = "MaxRects placement heuristic, or auto to keep the best of all (default: best_area_fit)"
+    )]
+    pub heuristic: PackHeuristic,
+    #[serde(default = "default_pack_free_rect_choice")]
+    #[schemars(
+        description = "Guillotine rule for choosing the free space a sprite goes into, or auto to keep the best of all (default: best_short_side_fit)"
+    )]
+    pub free_rect_choice: PackFreeRectChoice,
+    #[serde(default = "default_pack_split_rule")]
+    #[schemars(
+        description = "Guillotine rule for splitting the free space left around a sprite, or auto to keep the best of all (default: shorter_leftover_axis)"
+    )]
+    pub split_rule: PackSplitRule,
     #[schemars(
         description = "Maximum number of atlas pages to generate (optional, unlimited by default)"
     )]
     pub page_limit: Option<u32>,
+    #[serde(default = "default_pack_overflow")]
+    #[schemars(
+        description = "What to do with sprites that are too large or exceed the page limit (default: error)"
+    )]
+    pub overflow: PackOverflow,
     #[serde(default = "default_pack_sort")]
     #[schemars(description = "Sprite sorting method for deterministic packing (default: area)")]
     pub sort: PackSort,
     #[schemars(description = "Enable deduplication of identical sprites (default: false)")]
     pub dedupe: bool,
+    #[schemars(
+        description = "Allow rotating sprites by 90 degrees to pack them tighter (default: false)"
+    )]
+    pub allow_rotation: bool,
+    #[schemars(
+        description = "Keep unchanged sprites where they are and only re-upload the atlas pages that changed, always using MaxRects for the new sprites (default: false)"
+    )]
+    pub incremental: bool,
+    #[schemars(
+        description = "Groups of sprites packed onto their own atlas pages, mapped by name. Sprites go to the first group in name order whose glob matches them, the rest share the input's pages"
+    )]
+    pub groups: BTreeMap<String, PackGroup>,
+    #[schemars(
+        description = "Write each atlas page and a JSON manifest of the layout to disk (default: false)"
+    )]
+    pub write_atlases: bool,
+    #[schemars(
+        description = "Directory to write atlases and their manifest to (defaults to the input's output_path)"
+    )]
+    pub atlas_dir: Option<PathBuf>,
+    #[schemars(
+        description = "Group numbered frames such as walk_0.png, walk_1.png into flipbook sequences packed onto one page (default: false)"
+    )]
+    pub sequences: bool,
+    #[serde(default = "default_sequence_pattern")]
+    #[schemars(
+        description = "Regex matched against file names without extension to find sequence frames. The 'name' group names the sequence and the 'frame' group is the frame number (default: ^(?P<name>.+)[_-](?P<frame>\\d+)$)"
+    )]
+    pub sequence_pattern: String,
 }
 
 impl Default for PackOptions {
@@ -248,13 +620,86 @@ impl Default for PackOptions {
             enabled: false,
             max_size: default_pack_max_size(),
             power_of_two: default_pack_power_of_two(),
+            shrink_to_fit: true,
             padding: default_pack_padding(),
             extrude: default_pack_extrude(),
             allow_trim: false,
             algorithm: default_pack_algorithm(),
+            heuristic: default_pack_heuristic(),
+            free_rect_choice: default_pack_free_rect_choice(),
+            split_rule: default_pack_split_rule(),
             page_limit: None,
+            overflow: default_pack_overflow(),
             sort: default_pack_sort(),
             dedupe: false,
+            allow_rotation: false,
+            incremental: false,
+            groups: BTreeMap::new(),
+            write_atlases: false,
+            atlas_dir: None,
+            sequences: false,
+            sequence_pattern: default_sequence_pattern(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[schemars(description = "A group of sprites packed onto their own atlas pages")]
+pub struct PackGroup {
+    #[schemars(with = "String")]
+    #[schemars(
+        description = "Glob pattern matching sprite paths relative to the input (e.g., 'hud/**')"
+    )]
+    pub glob: Glob,
+    #[schemars(description = "Maximum atlas size for this group (defaults to the input's)")]
+    pub max_size: Option<(u32, u32)>,
+    #[schemars(description = "Constrain this group's atlases to power-of-two sizes")]
+    pub power_of_two: Option<bool>,
+    #[schemars(description = "Shrink this group's atlas pages to fit their sprites")]
+    pub shrink_to_fit: Option<bool>,
+    #[schemars(description = "Padding between sprites in this group")]
+    pub padding: Option<u32>,
+    #[schemars(description = "Pixels to extrude sprite edges in this group")]
+    pub extrude: Option<u32>,
+    #[schemars(description = "Packing algorithm for this group")]
+    pub algorithm: Option<PackAlgorithm>,
+    #[schemars(description = "MaxRects placement heuristic for this group")]
+    pub heuristic: Option<PackHeuristic>,
+    #[schemars(description = "Guillotine free space rule for this group")]
+    pub free_rect_choice: Option<PackFreeRectChoice>,
+    #[schemars(description = "Guillotine split rule for this group")]
+    pub split_rule: Option<PackSplitRule>,
+    #[schemars(description = "Maximum number of atlas pages for this group")]
+    pub page_limit: Option<u32>,
+    #[schemars(description = "Sprite sorting method for this group")]
+    pub sort: Option<PackSort>,
+    #[schemars(description = "Allow rotating sprites in this group")]
+    pub allow_rotation: Option<bool>,
+}
+
+impl PackGroup {
+    /// The input's pack options with this group's overrides applied
+    pub fn resolve(&self, base: &PackOptions) -> PackOptions {
+        let base = base.clone();
+
+        PackOptions {
+            max_size: self.max_size.unwrap_or(base.max_size),
+            power_of_two: self.power_of_two.unwrap_or(base.power_of_two),
+            shrink_to_fit: self.shrink_to_fit.unwrap_or(base.shrink_to_fit),
+            padding: self.padding.unwrap_or(base.padding),
+            extrude: self.extrude.unwrap_or(base.extrude),
+            algorithm: self.algorithm.clone().unwrap_or(base.algorithm),
+            heuristic: self.heuristic.clone().unwrap_or(base.heuristic),
+            free_rect_choice: self
+                .free_rect_choice
+                .clone()
+                .unwrap_or(base.free_rect_choice),
+            split_rule: self.split_rule.clone().unwrap_or(base.split_rule),
+            page_limit: self.page_limit.or(base.page_limit),
+            sort: self.sort.clone().unwrap_or(base.sort),
+            allow_rotation: self.allow_rotation.unwrap_or(base.allow_rotation),
+            groups: BTreeMap::new(),
+            ..base
         }
     }
 }
@@ -263,10 +708,74 @@ impl Default for PackOptions {
 #[serde(rename_all = "snake_case")]
 #[schemars(description = "Packing algorithm to use")]
 pub enum PackAlgorithm {
+    /// Tracks every maximal free rectangle for the tightest layouts
     MaxRects,
+    /// Splits free space with straight cuts, faster with row-like layouts
     Guillotine,
 }
 
+#[derive(Debug, Deserialize, Clone, ValueEnum, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(description = "MaxRects placement heuristic")]
+pub enum PackHeuristic {
+    /// Minimize the leftover on the shorter side of the free space
+    BestShortSideFit,
+    /// Minimize the leftover on the longer side of the free space
+    BestLongSideFit,
+    /// Minimize the leftover area of the free space
+    BestAreaFit,
+    /// Place sprites as close to the top-left corner as possible
+    BottomLeft,
+    /// Maximize contact with the atlas edges and other sprites
+    ContactPoint,
+    /// Try every heuristic and keep the layout with the fewest, fullest pages
+    Auto,
+}
+
+#[derive(Debug, Deserialize, Clone, ValueEnum, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(description = "Guillotine rule for choosing the free space a sprite goes into")]
+pub enum PackFreeRectChoice {
+    /// Minimize the leftover area of the free space
+    BestAreaFit,
+    /// Minimize the leftover on the shorter side of the free space
+    BestShortSideFit,
+    /// Minimize the leftover on the longer side of the free space
+    BestLongSideFit,
+    /// Try every rule and keep the layout with the fewest, fullest pages
+    Auto,
+}
+
+#[derive(Debug, Deserialize, Clone, ValueEnum, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(description = "Guillotine rule for splitting the free space left around a sprite")]
+pub enum PackSplitRule {
+    /// Cut along the axis with the shorter leftover
+    ShorterLeftoverAxis,
+    /// Cut along the axis with the longer leftover
+    LongerLeftoverAxis,
+    /// Cut along the shorter side of the free space
+    ShorterAxis,
+    /// Cut along the longer side of the free space
+    LongerAxis,
+    /// Keep the smaller of the two leftover pieces as small as possible
+    MinimizeArea,
+    /// Keep the larger of the two leftover pieces as large as possible
+    MaximizeArea,
+    /// Try every rule and keep the layout with the fewest, fullest pages
+    Auto,
+}
+
+#[derive(Debug, Deserialize, Clone, ValueEnum, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(description = "Policy for sprites that cannot be packed")]
+pub enum PackOverflow {
+    /// Fail the sync
+    Error,
+    /// Upload the sprites as regular, unpacked images
+    UploadIndividually,
+}
+
 #[derive(Debug, Deserialize, Clone, ValueEnum, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 #[schemars(description = "Sprite sorting method for deterministic packing")]
@@ -276,6 +785,17 @@ pub enum PackSort {
     Name,
 }
 
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(description = "How changed files are uploaded")]
+pub enum UpdateStrategy {
+    #[default]
+    /// Upload each change as a new asset with its own ID
+    NewAsset,
+    /// Upload each change as a new version of the same asset, keeping its ID
+    NewVersion,
+}
+
 #[derive(Debug, Deserialize, Default, Clone, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 #[schemars(description = "Code generation style")]
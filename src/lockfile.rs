@@ -1,6 +1,7 @@
 use anyhow::{Context, Result, bail};
 use blake3::Hasher;
 use fs_err::tokio as fs;
+use relative_path::{RelativePath, RelativePathBuf};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
@@ -13,11 +14,24 @@ pub const FILE_NAME: &str = "asphalt.lock.toml";
 pub struct Lockfile {
     version: u32,
     inputs: BTreeMap<String, BTreeMap<String, LockfileEntry>>,
+    /// Uploads Roblox hadn't finished processing when a sync gave up waiting
+    /// on them, resumed by the next sync instead of uploading again
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pending: BTreeMap<String, BTreeMap<String, PendingOperation>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingOperation {
+    pub operation_id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LockfileEntry {
     pub asset_id: u64,
+    /// The path the asset was uploaded from, so a new version of it can be
+    /// uploaded when the file changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<RelativePathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sprite_info: Option<SpriteInfo>,
 }
@@ -28,6 +42,22 @@ pub struct SpriteInfo {
     pub source_size: crate::pack::rect::Size,
     pub trimmed: bool,
     pub sprite_source_size: Option<crate::pack::rect::Rect>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rotated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slice_center: Option<crate::pack::rect::Rect>,
+    /// The atlas page the sprite was packed onto, so later syncs can rebuild
+    /// the layout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<SpritePage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpritePage {
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub size: crate::pack::rect::Size,
 }
 
 impl Default for Lockfile {
@@ -35,6 +65,7 @@ impl Default for Lockfile {
         Self {
             version: 3,
             inputs: BTreeMap::new(),
+            pending: BTreeMap::new(),
         }
     }
 }
@@ -44,11 +75,61 @@ impl Lockfile {
         self.inputs.get(input_name).and_then(|m| m.get(hash))
     }
 
+    /// Inserting an entry settles any pending upload of the same asset
     pub fn insert(&mut self, input_name: &str, hash: &str, entry: LockfileEntry) {
         self.inputs
             .entry(input_name.to_string())
             .or_default()
             .insert(hash.to_owned(), entry);
+
+        self.remove_pending(input_name, hash);
+    }
+
+    /// The entry of the asset last uploaded from a path
+    pub fn find_by_path(&self, input_name: &str, path: &RelativePath) -> Option<&LockfileEntry> {
+        self.inputs
+            .get(input_name)?
+            .values()
+            .find(|entry| entry.path.as_deref() == Some(path))
+    }
+
+    /// The entries of the sprites packed into an input's atlas pages
+    pub fn sprite_entries(&self, input_name: &str) -> impl Iterator<Item = (&str, &LockfileEntry)> {
+        self.inputs
+            .get(input_name)
+            .into_iter()
+            .flatten()
+            .filter(|(_, entry)| entry.sprite_info.is_some())
+            .map(|(hash, entry)| (hash.as_str(), entry))
+    }
+
+    pub fn remove_sprite_entries(&mut self, input_name: &str) {
+        if let Some(entries) = self.inputs.get_mut(input_name) {
+            entries.retain(|_, entry| entry.sprite_info.is_none());
+            if entries.is_empty() {
+                self.inputs.remove(input_name);
+            }
+        }
+    }
+
+    pub fn get_pending(&self, input_name: &str, hash: &str) -> Option<&PendingOperation> {
+        self.pending.get(input_name).and_then(|m| m.get(hash))
+    }
+
+    pub fn remove_pending(&mut self, input_name: &str, hash: &str) {
+        if let Some(pending) = self.pending.get_mut(input_name) {
+            pending.remove(hash);
+            if pending.is_empty() {
+                self.pending.remove(input_name);
+            }
+        }
+    }
+
+    pub fn insert_pending(&mut self, input_name: &str, hash: &str, operation: PendingOperation) {
+        self.pending
+            .entry(input_name.to_string())
+            .or_default()
+            .insert(hash.to_owned(), operation);
     }
 
     pub fn count_entries(&self) -> usize {
@@ -59,7 +140,12 @@ impl Lockfile {
         let mut content = toml::to_string(self)?;
         content.insert_str(0, "# This file is automatically @generated by Asphalt.\n# It is not intended for manual editing.\n");
 
-        fs::write(filename.unwrap_or(Path::new(FILE_NAME)), content).await?;
+        // Write to a temporary file first so an interrupted sync never leaves a
+        // half-written lockfile behind.
+        let path = filename.unwrap_or(Path::new(FILE_NAME));
+        let temp_path = path.with_extension("toml.tmp");
+        fs::write(&temp_path, content).await?;
+        fs::rename(&temp_path, path).await?;
         Ok(())
     }
 }
@@ -158,6 +244,7 @@ fn migrate_from_v2(lockfile: &LockfileV2) -> Lockfile {
                 hash,
                 LockfileEntry {
                     asset_id: entry.asset_id,
+                    path: None,
                     sprite_info: None,
                 },
             )
@@ -177,6 +264,7 @@ fn migrate_from_v1(lockfile: &LockfileV1) -> Lockfile {
                 &entry.hash,
                 LockfileEntry {
                     asset_id: entry.asset_id,
+                    path: None,
                     sprite_info: None,
                 },
             )
@@ -199,6 +287,7 @@ async fn migrate_from_v0(lockfile: &LockfileV0, input_name: &str) -> anyhow::Res
             &new_hash,
             LockfileEntry {
                 asset_id: entry.asset_id,
+                path: None,
                 sprite_info: None,
             },
         )
@@ -229,6 +318,9 @@ mod tests {
             } else {
                 None
             },
+            rotated: false,
+            slice_center: None,
+            page: None,
         }
     }
 
@@ -238,11 +330,13 @@ mod tests {
 
         let entry_with_sprite = LockfileEntry {
             asset_id: 12345,
+            path: None,
             sprite_info: Some(create_sprite_info(true)),
         };
 
         let entry_without_sprite = LockfileEntry {
             asset_id: 67890,
+            path: None,
             sprite_info: None,
         };
 
@@ -265,6 +359,93 @@ mod tests {
         assert!(retrieved_without.sprite_info.is_none());
     }
 
+    #[test]
+    fn test_remove_sprite_entries() {
+        let mut lockfile = Lockfile::default();
+        lockfile.insert(
+            "images",
+            "hash1",
+            LockfileEntry {
+                asset_id: 1,
+                path: None,
+                sprite_info: Some(create_sprite_info(false)),
+            },
+        );
+        lockfile.insert(
+            "images",
+            "hash2",
+            LockfileEntry {
+                asset_id: 2,
+                path: None,
+                sprite_info: None,
+            },
+        );
+
+        let sprites: Vec<_> = lockfile.sprite_entries("images").collect();
+        assert_eq!(sprites.len(), 1);
+        assert_eq!(sprites[0].0, "hash1");
+
+        lockfile.remove_sprite_entries("images");
+        assert!(lockfile.get("images", "hash1").is_none());
+        assert!(lockfile.get("images", "hash2").is_some());
+    }
+
+    #[test]
+    fn test_pending_operations() {
+        let mut lockfile = Lockfile::default();
+        lockfile.insert_pending(
+            "images",
+            "hash1",
+            PendingOperation {
+                operation_id: "op1".to_string(),
+            },
+        );
+
+        let serialized = toml::to_string(&lockfile).unwrap();
+        let deserialized: Lockfile = toml::from_str(&serialized).unwrap();
+        let pending = deserialized.get_pending("images", "hash1").unwrap();
+        assert_eq!(pending.operation_id, "op1");
+
+        lockfile.insert(
+            "images",
+            "hash1",
+            LockfileEntry {
+                asset_id: 1,
+                path: None,
+                sprite_info: None,
+            },
+        );
+        assert!(lockfile.get_pending("images", "hash1").is_none());
+        assert!(!toml::to_string(&lockfile).unwrap().contains("pending"));
+    }
+
+    #[test]
+    fn test_find_by_path() {
+        let mut lockfile = Lockfile::default();
+        lockfile.insert(
+            "images",
+            "hash1",
+            LockfileEntry {
+                asset_id: 1,
+                path: Some(RelativePathBuf::from("ui/close.png")),
+                sprite_info: None,
+            },
+        );
+
+        let serialized = toml::to_string(&lockfile).unwrap();
+        let deserialized: Lockfile = toml::from_str(&serialized).unwrap();
+
+        let entry = deserialized
+            .find_by_path("images", RelativePath::new("ui/close.png"))
+            .unwrap();
+        assert_eq!(entry.asset_id, 1);
+        assert!(
+            deserialized
+                .find_by_path("images", RelativePath::new("ui/open.png"))
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_lockfile_sprite_info_serialization() {
         let mut lockfile = Lockfile::default();
@@ -274,6 +455,7 @@ mod tests {
             "hash_trimmed",
             LockfileEntry {
                 asset_id: 11111,
+                path: None,
                 sprite_info: Some(create_sprite_info(true)),
             },
         );
@@ -283,6 +465,7 @@ mod tests {
             "hash_untrimmed",
             LockfileEntry {
                 asset_id: 22222,
+                path: None,
                 sprite_info: Some(create_sprite_info(false)),
             },
         );
@@ -292,6 +475,7 @@ mod tests {
             "hash_regular",
             LockfileEntry {
                 asset_id: 33333,
+                path: None,
                 sprite_info: None,
             },
         );
@@ -349,6 +533,7 @@ mod tests {
             "hash_a",
             LockfileEntry {
                 asset_id: 100,
+                path: None,
                 sprite_info: Some(create_sprite_info(true)),
             },
         );
@@ -358,6 +543,7 @@ mod tests {
             "hash_b",
             LockfileEntry {
                 asset_id: 200,
+                path: None,
                 sprite_info: Some(create_sprite_info(false)),
             },
         );
@@ -367,6 +553,7 @@ mod tests {
             "hash_c",
             LockfileEntry {
                 asset_id: 300,
+                path: None,
                 sprite_info: None,
             },
         );
@@ -387,6 +574,13 @@ mod tests {
             source_size: Size::new(u32::MAX, u32::MAX),
             trimmed: true,
             sprite_source_size: Some(Rect::new(0, 0, u32::MAX, u32::MAX)),
+            rotated: true,
+            slice_center: Some(Rect::new(0, 0, u32::MAX, u32::MAX)),
+            page: Some(SpritePage {
+                index: usize::MAX,
+                group: Some("hud".to_string()),
+                size: Size::new(u32::MAX, u32::MAX),
+            }),
         };
 
         lockfile.insert(
@@ -394,6 +588,7 @@ mod tests {
             "max_values",
             LockfileEntry {
                 asset_id: u64::MAX,
+                path: None,
                 sprite_info: Some(max_values_sprite),
             },
         );
@@ -409,6 +604,9 @@ mod tests {
             source_size: Size::new(1, 1),
             trimmed: false,
             sprite_source_size: None,
+            rotated: false,
+            slice_center: None,
+            page: None,
         };
 
         lockfile.insert(
@@ -416,6 +614,7 @@ mod tests {
             "zero_values",
             LockfileEntry {
                 asset_id: 0,
+                path: None,
                 sprite_info: Some(zero_values_sprite),
             },
         );
@@ -426,4 +625,46 @@ mod tests {
         assert_eq!(sprite.rect.x, 0);
         assert_eq!(sprite.rect.y, 0);
     }
+
+    #[test]
+    fn test_sprite_info_rotated_defaults_to_false() {
+        let mut lockfile = Lockfile::default();
+
+        let mut rotated = create_sprite_info(false);
+        rotated.rotated = true;
+
+        lockfile.insert(
+            "sprites",
+            "hash_rotated",
+            LockfileEntry {
+                asset_id: 1,
+                path: None,
+                sprite_info: Some(rotated),
+            },
+        );
+        lockfile.insert(
+            "sprites",
+            "hash_upright",
+            LockfileEntry {
+                asset_id: 2,
+                path: None,
+                sprite_info: Some(create_sprite_info(false)),
+            },
+        );
+
+        let serialized = toml::to_string(&lockfile).expect("Failed to serialize");
+        assert_eq!(serialized.matches("rotated = true").count(), 1);
+        assert!(!serialized.contains("rotated = false"));
+
+        let deserialized: Lockfile = toml::from_str(&serialized).expect("Failed to deserialize");
+        let get_rotated = |hash| {
+            deserialized
+                .get("sprites", hash)
+                .and_then(|entry| entry.sprite_info.as_ref())
+                .unwrap()
+                .rotated
+        };
+        assert!(get_rotated("hash_rotated"));
+        assert!(!get_rotated("hash_upright"));
+    }
 }
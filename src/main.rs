@@ -8,7 +8,7 @@ use log::LevelFilter;
 use miette::{IntoDiagnostic, WrapErr};
 use migrate_lockfile::migrate_lockfile;
 use schemars::generate::SchemaSettings;
-use sync::sync;
+use sync::{pack_preview, repack, sync};
 use upload::upload;
 
 mod asset;
@@ -53,6 +53,12 @@ async fn main() -> miette::Result<()> {
         Commands::Sync(args) => sync(multi_progress, args)
             .await
             .map_err(|e| miette::miette!(e)),
+        Commands::Repack(args) => repack(multi_progress, args)
+            .await
+            .map_err(|e| miette::miette!(e)),
+        Commands::PackPreview(args) => pack_preview(multi_progress, args)
+            .await
+            .map_err(|e| miette::miette!(e)),
         Commands::Upload(args) => upload(args).await.map_err(|e| miette::miette!(e)),
         Commands::MigrateLockfile(args) => {
             migrate_lockfile(args).await.map_err(|e| miette::miette!(e))
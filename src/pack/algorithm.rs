@@ -1,14 +1,97 @@
 use crate::pack::rect::{Rect, Size};
 use std::collections::VecDeque;
 
+/// Common interface for rectangle packing algorithms
+pub trait RectPacker {
+    /// Try to pack a rectangle of the given size
+    /// Returns Some(Rect) with the position if successful, None if it doesn't fit.
+    /// When rotation is allowed the returned rect may have width and height swapped.
+    fn pack(&mut self, size: Size) -> Option<Rect>;
+
+    /// Like [`RectPacker::pack`], but never rotates the rectangle
+    fn pack_upright(&mut self, size: Size) -> Option<Rect>;
+
+    /// Mark a rectangle as already taken, such as a sprite kept from a previous layout
+    fn occupy(&mut self, rect: Rect);
+
+    /// Calculate the occupancy ratio (0.0 to 1.0)
+    #[allow(dead_code)]
+    fn occupancy(&self) -> f64;
+
+    /// Copy the packer's current state, so placements can be tried out and thrown away
+    fn boxed_clone(&self) -> Box<dyn RectPacker>;
+}
+
+/// The algorithm a packer uses along with the heuristics it places rectangles with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackerHeuristic {
+    MaxRects(MaxRectsHeuristic),
+    Guillotine(FreeRectChoice, SplitRule),
+}
+
+/// Create the packer for an algorithm and its heuristics
+pub fn create_packer(
+    heuristic: PackerHeuristic,
+    bin_size: Size,
+    allow_rotation: bool,
+) -> Box<dyn RectPacker> {
+    match heuristic {
+        PackerHeuristic::MaxRects(heuristic) => Box::new(
+            MaxRectsPacker::new(bin_size)
+                .with_heuristic(heuristic)
+                .with_rotation(allow_rotation),
+        ),
+        PackerHeuristic::Guillotine(choice, split) => Box::new(
+            GuillotinePacker::with_heuristics(bin_size, choice, split)
+                .with_rotation(allow_rotation),
+        ),
+    }
+}
+
+/// The orientations a rectangle of the given size may be placed in
+fn orientations(size: Size, allow_rotation: bool) -> impl Iterator<Item = Size> {
+    let rotated = (allow_rotation && size.width != size.height).then(|| size.rotated());
+    std::iter::once(size).chain(rotated)
+}
+
+/// Placement rule used by MaxRects to score candidate positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxRectsHeuristic {
+    /// Minimize the leftover on the shorter side of the free rectangle
+    BestShortSideFit,
+    /// Minimize the leftover on the longer side of the free rectangle
+    BestLongSideFit,
+    /// Minimize the leftover area of the free rectangle
+    BestAreaFit,
+    /// Place as close to the top-left corner of the bin as possible (Tetris-style)
+    BottomLeft,
+    /// Maximize the edge length touching the bin or already placed rectangles
+    ContactPoint,
+}
+
+impl MaxRectsHeuristic {
+    pub const ALL: &[MaxRectsHeuristic] = &[
+        MaxRectsHeuristic::BestShortSideFit,
+        MaxRectsHeuristic::BestLongSideFit,
+        MaxRectsHeuristic::BestAreaFit,
+        MaxRectsHeuristic::BottomLeft,
+        MaxRectsHeuristic::ContactPoint,
+    ];
+}
+
 /// MaxRects algorithm implementation for rectangle packing
 ///
-/// This implements the MaxRects algorithm with Best Area Fit heuristic
-/// for deterministic and efficient rectangle packing.
+/// This implements the MaxRects algorithm with a selectable placement
+/// heuristic (Best Area Fit by default) for deterministic and efficient
+/// rectangle packing.
+#[derive(Clone)]
 pub struct MaxRectsPacker {
     bin_size: Size,
     free_rects: VecDeque<Rect>,
+    used_rects: Vec<Rect>,
     used_area: u32,
+    allow_rotation: bool,
+    heuristic: MaxRectsHeuristic,
 }
 
 impl MaxRectsPacker {
@@ -20,43 +103,43 @@ impl MaxRectsPacker {
         Self {
             bin_size,
             free_rects,
+            used_rects: Vec::new(),
             used_area: 0,
+            allow_rotation: false,
+            heuristic: MaxRectsHeuristic::BestAreaFit,
         }
     }
 
-    /// Try to pack a rectangle of the given size
-    /// Returns Some(Rect) with the position if successful, None if it doesn't fit
-    pub fn pack(&mut self, size: Size) -> Option<Rect> {
-        if size.width > self.bin_size.width || size.height > self.bin_size.height {
-            return None;
-        }
+    /// Allow rectangles to be rotated by 90 degrees when that fits better
+    pub fn with_rotation(mut self, allow_rotation: bool) -> Self {
+        self.allow_rotation = allow_rotation;
+        self
+    }
 
-        let best_rect = self.find_best_position(size)?;
-        self.place_rect(best_rect);
-        self.used_area += best_rect.area();
-        Some(best_rect)
+    /// Use the given placement heuristic
+    pub fn with_heuristic(mut self, heuristic: MaxRectsHeuristic) -> Self {
+        self.heuristic = heuristic;
+        self
     }
 
-    /// Find the best position for a rectangle using Best Area Fit heuristic
+    /// Find the best position for a rectangle using the configured heuristic
     fn find_best_position(&self, size: Size) -> Option<Rect> {
         let mut best_rect = None;
-        let mut best_area_fit = u32::MAX;
-        let mut best_short_side_fit = u32::MAX;
+        let mut best_score = (u32::MAX, u32::MAX);
 
         for free_rect in &self.free_rects {
-            if size.fits_in(free_rect.size()) {
-                let area_fit = free_rect.area() - size.area();
-                let leftover_horizontal = free_rect.width - size.width;
-                let leftover_vertical = free_rect.height - size.height;
-                let short_side_fit = leftover_horizontal.min(leftover_vertical);
-
-                // Best Area Fit with Short Side Fit as tie-breaker
-                if area_fit < best_area_fit
-                    || (area_fit == best_area_fit && short_side_fit < best_short_side_fit)
-                {
-                    best_rect = Some(Rect::new(free_rect.x, free_rect.y, size.width, size.height));
-                    best_area_fit = area_fit;
-                    best_short_side_fit = short_side_fit;
+            for size in orientations(size, self.allow_rotation) {
+                if !size.fits_in(free_rect.size()) {
+                    continue;
+                }
+
+                let candidate = Rect::new(free_rect.x, free_rect.y, size.width, size.height);
+                let score = self.score(free_rect, &candidate);
+
+                // Lower scores are better; ties keep the earliest candidate
+                if score < best_score {
+                    best_rect = Some(candidate);
+                    best_score = score;
                 }
             }
         }
@@ -64,6 +147,54 @@ impl MaxRectsPacker {
         best_rect
     }
 
+    /// Score placing `candidate` in `free_rect` as (primary, tie-breaker), lower is better
+    fn score(&self, free_rect: &Rect, candidate: &Rect) -> (u32, u32) {
+        let leftover_horizontal = free_rect.width - candidate.width;
+        let leftover_vertical = free_rect.height - candidate.height;
+        let short_side_fit = leftover_horizontal.min(leftover_vertical);
+        let long_side_fit = leftover_horizontal.max(leftover_vertical);
+
+        match self.heuristic {
+            MaxRectsHeuristic::BestShortSideFit => (short_side_fit, long_side_fit),
+            MaxRectsHeuristic::BestLongSideFit => (long_side_fit, short_side_fit),
+            MaxRectsHeuristic::BestAreaFit => (free_rect.area() - candidate.area(), short_side_fit),
+            MaxRectsHeuristic::BottomLeft => (candidate.bottom(), candidate.x),
+            MaxRectsHeuristic::ContactPoint => {
+                (u32::MAX - self.contact_score(candidate), short_side_fit)
+            }
+        }
+    }
+
+    /// Total edge length of `rect` touching the bin edges or already placed rectangles
+    fn contact_score(&self, rect: &Rect) -> u32 {
+        let mut score = 0;
+
+        // A rect as wide or as tall as the bin touches both of its edges
+        if rect.x == 0 {
+            score += rect.height;
+        }
+        if rect.right() == self.bin_size.width {
+            score += rect.height;
+        }
+        if rect.y == 0 {
+            score += rect.width;
+        }
+        if rect.bottom() == self.bin_size.height {
+            score += rect.width;
+        }
+
+        for used in &self.used_rects {
+            if used.x == rect.right() || used.right() == rect.x {
+                score += common_interval_length(used.y, used.bottom(), rect.y, rect.bottom());
+            }
+            if used.y == rect.bottom() || used.bottom() == rect.y {
+                score += common_interval_length(used.x, used.right(), rect.x, rect.right());
+            }
+        }
+
+        score
+    }
+
     /// Place a rectangle and update the free rectangle list
     fn place_rect(&mut self, placed_rect: Rect) {
         let mut new_free_rects = VecDeque::new();
@@ -152,13 +283,314 @@ impl MaxRectsPacker {
     pub fn free_area(&self) -> u32 {
         self.free_rects.iter().map(|r| r.area()).sum()
     }
+}
 
-    /// Calculate the occupancy ratio (0.0 to 1.0)
+impl RectPacker for MaxRectsPacker {
+    fn pack(&mut self, size: Size) -> Option<Rect> {
+        if !orientations(size, self.allow_rotation).any(|size| size.fits_in(self.bin_size)) {
+            return None;
+        }
+
+        let best_rect = self.find_best_position(size)?;
+        self.place_rect(best_rect);
+        self.used_rects.push(best_rect);
+        self.used_area += best_rect.area();
+        Some(best_rect)
+    }
+
+    fn pack_upright(&mut self, size: Size) -> Option<Rect> {
+        let allow_rotation = std::mem::replace(&mut self.allow_rotation, false);
+        let rect = self.pack(size);
+        self.allow_rotation = allow_rotation;
+        rect
+    }
+
+    fn occupy(&mut self, rect: Rect) {
+        self.place_rect(rect);
+        self.used_rects.push(rect);
+        self.used_area += rect.area();
+    }
+
+    fn occupancy(&self) -> f64 {
+        let total_area = self.bin_size.area() as f64;
+        self.used_area as f64 / total_area
+    }
+
+    fn boxed_clone(&self) -> Box<dyn RectPacker> {
+        Box::new(self.clone())
+    }
+}
+
+/// Length of the overlap between the intervals [a_start, a_end) and [b_start, b_end)
+fn common_interval_length(a_start: u32, a_end: u32, b_start: u32, b_end: u32) -> u32 {
+    if a_end < b_start || b_end < a_start {
+        return 0;
+    }
+
+    a_end.min(b_end) - a_start.max(b_start)
+}
+
+/// Heuristic used to choose which free rectangle a new rectangle goes into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum FreeRectChoice {
+    /// Pick the free rectangle with the least leftover area
+    BestAreaFit,
+    /// Pick the free rectangle with the smallest leftover on its shorter side
+    BestShortSideFit,
+    /// Pick the free rectangle with the smallest leftover on its longer side
+    BestLongSideFit,
+}
+
+impl FreeRectChoice {
+    pub const ALL: &[FreeRectChoice] = &[
+        FreeRectChoice::BestAreaFit,
+        FreeRectChoice::BestShortSideFit,
+        FreeRectChoice::BestLongSideFit,
+    ];
+}
+
+/// Rule used to split the leftover space of a free rectangle after placement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitRule {
+    /// Split along the axis with the shorter leftover length
+    ShorterLeftoverAxis,
+    /// Split along the axis with the longer leftover length
+    LongerLeftoverAxis,
+    /// Split along the shorter side of the free rectangle
+    ShorterAxis,
+    /// Split along the longer side of the free rectangle
+    LongerAxis,
+    /// Split so that the smaller of the two new rectangles is as small as possible
+    MinimizeArea,
+    /// Split so that the larger of the two new rectangles is as large as possible
+    MaximizeArea,
+}
+
+impl SplitRule {
+    pub const ALL: &[SplitRule] = &[
+        SplitRule::ShorterLeftoverAxis,
+        SplitRule::LongerLeftoverAxis,
+        SplitRule::ShorterAxis,
+        SplitRule::LongerAxis,
+        SplitRule::MinimizeArea,
+        SplitRule::MaximizeArea,
+    ];
+}
+
+/// Guillotine algorithm implementation for rectangle packing
+///
+/// Every placement cuts the chosen free rectangle in two with a single
+/// straight cut, so free rectangles never overlap. This is faster than
+/// MaxRects and produces row/column-like layouts at the cost of density.
+#[derive(Clone)]
+pub struct GuillotinePacker {
+    bin_size: Size,
+    free_rects: Vec<Rect>,
+    used_area: u32,
+    choice: FreeRectChoice,
+    split: SplitRule,
+    allow_rotation: bool,
+}
+
+impl GuillotinePacker {
+    /// Create a new Guillotine packer with explicit heuristics
+    pub fn with_heuristics(bin_size: Size, choice: FreeRectChoice, split: SplitRule) -> Self {
+        Self {
+            bin_size,
+            free_rects: vec![Rect::from_size(bin_size)],
+            used_area: 0,
+            choice,
+            split,
+            allow_rotation: false,
+        }
+    }
+
+    /// Allow rectangles to be rotated by 90 degrees when that fits better
+    pub fn with_rotation(mut self, allow_rotation: bool) -> Self {
+        self.allow_rotation = allow_rotation;
+        self
+    }
+
+    /// Find the index of the free rectangle that best fits the given size,
+    /// along with the orientation the rectangle should be placed in
+    fn find_best_free_rect(&self, size: Size) -> Option<(usize, Size)> {
+        let mut best = None;
+        let mut best_score = (u32::MAX, u32::MAX);
+
+        for (i, free_rect) in self.free_rects.iter().enumerate() {
+            for size in orientations(size, self.allow_rotation) {
+                if !size.fits_in(free_rect.size()) {
+                    continue;
+                }
+
+                let leftover_horizontal = free_rect.width - size.width;
+                let leftover_vertical = free_rect.height - size.height;
+                let short_side = leftover_horizontal.min(leftover_vertical);
+                let long_side = leftover_horizontal.max(leftover_vertical);
+                let area_fit = free_rect.area() - size.area();
+
+                let score = match self.choice {
+                    FreeRectChoice::BestAreaFit => (area_fit, short_side),
+                    FreeRectChoice::BestShortSideFit => (short_side, long_side),
+                    FreeRectChoice::BestLongSideFit => (long_side, short_side),
+                };
+
+                // Strict comparison keeps the earliest candidate on ties for determinism
+                if score < best_score {
+                    best = Some((i, size));
+                    best_score = score;
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Split the leftover space of a free rectangle into at most two new free rectangles
+    fn split_free_rect(&mut self, free_rect: &Rect, placed: &Rect) {
+        let leftover_width = free_rect.width - placed.width;
+        let leftover_height = free_rect.height - placed.height;
+
+        let split_horizontal = match self.split {
+            SplitRule::ShorterLeftoverAxis => leftover_width <= leftover_height,
+            SplitRule::LongerLeftoverAxis => leftover_width > leftover_height,
+            SplitRule::ShorterAxis => free_rect.width <= free_rect.height,
+            SplitRule::LongerAxis => free_rect.width > free_rect.height,
+            SplitRule::MinimizeArea => {
+                placed.width * leftover_height > leftover_width * placed.height
+            }
+            SplitRule::MaximizeArea => {
+                placed.width * leftover_height <= leftover_width * placed.height
+            }
+        };
+
+        let (bottom, right) = if split_horizontal {
+            (
+                Rect::new(
+                    free_rect.x,
+                    placed.bottom(),
+                    free_rect.width,
+                    leftover_height,
+                ),
+                Rect::new(placed.right(), free_rect.y, leftover_width, placed.height),
+            )
+        } else {
+            (
+                Rect::new(free_rect.x, placed.bottom(), placed.width, leftover_height),
+                Rect::new(
+                    placed.right(),
+                    free_rect.y,
+                    leftover_width,
+                    free_rect.height,
+                ),
+            )
+        };
+
+        for rect in [bottom, right] {
+            if rect.width > 0 && rect.height > 0 {
+                self.free_rects.push(rect);
+            }
+        }
+    }
+
+    /// Merge free rectangles that share a full edge to reduce fragmentation
+    fn merge_free_rects(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            'outer: for i in 0..self.free_rects.len() {
+                for j in (i + 1)..self.free_rects.len() {
+                    if let Some(merged) = self.free_rects[i].try_merge_with(&self.free_rects[j]) {
+                        self.free_rects.remove(j);
+                        self.free_rects[i] = merged;
+                        changed = true;
+                        break 'outer;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get the current number of free rectangles (for testing/debugging)
+    #[allow(dead_code)]
+    pub fn free_rect_count(&self) -> usize {
+        self.free_rects.len()
+    }
+
+    /// Calculate the total free area remaining
     #[allow(dead_code)]
-    pub fn occupancy(&self) -> f64 {
+    pub fn free_area(&self) -> u32 {
+        self.free_rects.iter().map(|r| r.area()).sum()
+    }
+}
+
+impl RectPacker for GuillotinePacker {
+    fn pack(&mut self, size: Size) -> Option<Rect> {
+        if !orientations(size, self.allow_rotation).any(|size| size.fits_in(self.bin_size)) {
+            return None;
+        }
+
+        let (index, size) = self.find_best_free_rect(size)?;
+        let free_rect = self.free_rects.remove(index);
+        let placed = Rect::new(free_rect.x, free_rect.y, size.width, size.height);
+
+        self.split_free_rect(&free_rect, &placed);
+        self.merge_free_rects();
+        self.used_area += placed.area();
+
+        Some(placed)
+    }
+
+    fn pack_upright(&mut self, size: Size) -> Option<Rect> {
+        let allow_rotation = std::mem::replace(&mut self.allow_rotation, false);
+        let rect = self.pack(size);
+        self.allow_rotation = allow_rotation;
+        rect
+    }
+
+    fn occupy(&mut self, rect: Rect) {
+        let mut pieces = Vec::new();
+
+        // Cut the rectangle out of every free rectangle it touches, keeping the
+        // remaining pieces disjoint: full-width strips above and below, then the
+        // parts to the left and right of it
+        self.free_rects.retain(|free| {
+            if !free.intersects(&rect) {
+                return true;
+            }
+
+            let top = rect.y.max(free.y);
+            let bottom = rect.bottom().min(free.bottom());
+            pieces.extend([
+                Rect::new(free.x, free.y, free.width, top - free.y),
+                Rect::new(free.x, bottom, free.width, free.bottom() - bottom),
+                Rect::new(free.x, top, rect.x.saturating_sub(free.x), bottom - top),
+                Rect::new(
+                    rect.right(),
+                    top,
+                    free.right().saturating_sub(rect.right()),
+                    bottom - top,
+                ),
+            ]);
+            false
+        });
+
+        self.free_rects
+            .extend(pieces.into_iter().filter(|piece| piece.area() > 0));
+        self.merge_free_rects();
+        self.used_area += rect.area();
+    }
+
+    fn occupancy(&self) -> f64 {
         let total_area = self.bin_size.area() as f64;
         self.used_area as f64 / total_area
     }
+
+    fn boxed_clone(&self) -> Box<dyn RectPacker> {
+        Box::new(self.clone())
+    }
 }
 
 #[cfg(test)]
@@ -308,4 +740,252 @@ mod tests {
         // Free rect count may vary depending on splits and coalescing
         assert!(packer.free_rect_count() > 0);
     }
+
+    #[test]
+    fn test_guillotine_basic_packing() {
+        let mut packer = GuillotinePacker::with_heuristics(
+            Size::new(512, 512),
+            FreeRectChoice::BestShortSideFit,
+            SplitRule::ShorterLeftoverAxis,
+        );
+
+        let rect1 = packer.pack(Size::new(100, 100)).unwrap();
+        assert_eq!(rect1, Rect::new(0, 0, 100, 100));
+
+        let rect2 = packer.pack(Size::new(50, 50)).unwrap();
+        assert!(!rect1.intersects(&rect2));
+    }
+
+    #[test]
+    fn test_guillotine_oversized_and_full() {
+        let mut packer = GuillotinePacker::with_heuristics(
+            Size::new(100, 100),
+            FreeRectChoice::BestShortSideFit,
+            SplitRule::ShorterLeftoverAxis,
+        );
+        assert!(packer.pack(Size::new(200, 50)).is_none());
+
+        assert!(packer.pack(Size::new(100, 100)).is_some());
+        assert!(packer.pack(Size::new(10, 10)).is_none());
+        assert_eq!(packer.free_rect_count(), 0);
+        assert_eq!(packer.occupancy(), 1.0);
+    }
+
+    #[test]
+    fn test_guillotine_free_rects_never_overlap() {
+        let choices = [
+            FreeRectChoice::BestAreaFit,
+            FreeRectChoice::BestShortSideFit,
+            FreeRectChoice::BestLongSideFit,
+        ];
+        let splits = [
+            SplitRule::ShorterLeftoverAxis,
+            SplitRule::LongerLeftoverAxis,
+            SplitRule::ShorterAxis,
+            SplitRule::LongerAxis,
+            SplitRule::MinimizeArea,
+            SplitRule::MaximizeArea,
+        ];
+
+        for choice in choices {
+            for split in splits {
+                let mut packer =
+                    GuillotinePacker::with_heuristics(Size::new(256, 256), choice, split);
+                let mut packed_rects = Vec::new();
+
+                for i in 0..20 {
+                    let size = Size::new(16 + (i * 7) % 48, 16 + (i * 13) % 40);
+                    if let Some(rect) = packer.pack(size) {
+                        packed_rects.push(rect);
+                    }
+                }
+
+                let bin_rect = Rect::from_size(Size::new(256, 256));
+                for (i, rect) in packed_rects.iter().enumerate() {
+                    assert!(bin_rect.contains_rect(rect));
+                    for other in &packed_rects[i + 1..] {
+                        assert!(
+                            !rect.intersects(other),
+                            "{:?}/{:?}: {:?} overlaps {:?}",
+                            choice,
+                            split,
+                            rect,
+                            other
+                        );
+                    }
+                }
+
+                let used: u32 = packed_rects.iter().map(|r| r.area()).sum();
+                assert_eq!(used + packer.free_area(), 256 * 256);
+            }
+        }
+    }
+
+    const DISPATCHED: [PackerHeuristic; 2] = [
+        PackerHeuristic::MaxRects(MaxRectsHeuristic::BestAreaFit),
+        PackerHeuristic::Guillotine(FreeRectChoice::BestAreaFit, SplitRule::MinimizeArea),
+    ];
+
+    #[test]
+    fn test_create_packer_dispatch() {
+        for heuristic in DISPATCHED {
+            let mut packer = create_packer(heuristic, Size::new(100, 100), false);
+            assert!(packer.pack(Size::new(50, 50)).is_some());
+            assert_eq!(packer.occupancy(), 0.25);
+        }
+    }
+
+    #[test]
+    fn test_rotation_fits_tall_strip() {
+        for heuristic in DISPATCHED {
+            let mut packer = create_packer(heuristic, Size::new(100, 20), false);
+            assert!(packer.pack(Size::new(10, 100)).is_none());
+
+            let mut packer = create_packer(heuristic, Size::new(100, 20), true);
+            let rect = packer.pack(Size::new(10, 100)).unwrap();
+            assert_eq!(rect, Rect::new(0, 0, 100, 10));
+        }
+    }
+
+    #[test]
+    fn test_rotation_prefers_original_orientation_on_ties() {
+        let mut packer = MaxRectsPacker::new(Size::new(512, 512)).with_rotation(true);
+        let rect = packer.pack(Size::new(64, 32)).unwrap();
+        assert_eq!(rect.size(), Size::new(64, 32));
+    }
+
+    #[test]
+    fn test_heuristics_never_overlap() {
+        for &heuristic in MaxRectsHeuristic::ALL {
+            let mut packer = MaxRectsPacker::new(Size::new(256, 256)).with_heuristic(heuristic);
+            let mut packed_rects = Vec::new();
+
+            for i in 0..24 {
+                let size = Size::new(8 + (i * 11) % 56, 8 + (i * 17) % 44);
+                if let Some(rect) = packer.pack(size) {
+                    packed_rects.push(rect);
+                }
+            }
+
+            assert!(!packed_rects.is_empty());
+
+            let bin_rect = Rect::from_size(Size::new(256, 256));
+            for (i, rect) in packed_rects.iter().enumerate() {
+                assert!(bin_rect.contains_rect(rect));
+                for other in &packed_rects[i + 1..] {
+                    assert!(
+                        !rect.intersects(other),
+                        "{:?}: {:?} overlaps {:?}",
+                        heuristic,
+                        rect,
+                        other
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bottom_left_fills_rows() {
+        let mut packer =
+            MaxRectsPacker::new(Size::new(100, 100)).with_heuristic(MaxRectsHeuristic::BottomLeft);
+
+        assert_eq!(
+            packer.pack(Size::new(40, 20)),
+            Some(Rect::new(0, 0, 40, 20))
+        );
+        assert_eq!(
+            packer.pack(Size::new(40, 20)),
+            Some(Rect::new(40, 0, 40, 20))
+        );
+        assert_eq!(
+            packer.pack(Size::new(40, 20)),
+            Some(Rect::new(0, 20, 40, 20))
+        );
+    }
+
+    #[test]
+    fn test_contact_point_hugs_placed_rects() {
+        let mut packer = MaxRectsPacker::new(Size::new(100, 100))
+            .with_heuristic(MaxRectsHeuristic::ContactPoint);
+
+        packer.pack(Size::new(50, 50));
+        let rect = packer.pack(Size::new(50, 50)).unwrap();
+
+        // Touches the first rect and two bin edges rather than floating in a corner
+        assert!(rect == Rect::new(50, 0, 50, 50) || rect == Rect::new(0, 50, 50, 50));
+        assert_eq!(packer.contact_score(&Rect::new(50, 50, 50, 50)), 150);
+    }
+
+    #[test]
+    fn test_contact_score_counts_opposite_bin_edges() {
+        let packer = MaxRectsPacker::new(Size::new(100, 100));
+
+        assert_eq!(
+            packer.contact_score(&Rect::new(0, 0, 100, 20)),
+            20 + 20 + 100
+        );
+        assert_eq!(packer.contact_score(&Rect::new(0, 0, 100, 100)), 400);
+        assert_eq!(packer.contact_score(&Rect::new(10, 10, 20, 20)), 0);
+    }
+
+    #[test]
+    fn test_common_interval_length() {
+        assert_eq!(common_interval_length(0, 10, 5, 20), 5);
+        assert_eq!(common_interval_length(0, 10, 10, 20), 0);
+        assert_eq!(common_interval_length(0, 10, 20, 30), 0);
+        assert_eq!(common_interval_length(5, 8, 0, 100), 3);
+    }
+
+    #[test]
+    fn test_occupy_reserves_space() {
+        let mut packer = MaxRectsPacker::new(Size::new(100, 100));
+        packer.occupy(Rect::new(0, 0, 100, 60));
+
+        let rect = packer.pack(Size::new(100, 40)).unwrap();
+        assert_eq!(rect, Rect::new(0, 60, 100, 40));
+        assert!(packer.pack(Size::new(1, 1)).is_none());
+        assert_eq!(packer.occupancy(), 1.0);
+    }
+
+    #[test]
+    fn test_guillotine_occupy_keeps_free_rects_disjoint() {
+        let mut packer = GuillotinePacker::with_heuristics(
+            Size::new(100, 100),
+            FreeRectChoice::BestAreaFit,
+            SplitRule::ShorterLeftoverAxis,
+        );
+        let occupied = [Rect::new(20, 20, 30, 30), Rect::new(40, 60, 60, 40)];
+        for rect in occupied {
+            packer.occupy(rect);
+        }
+
+        let used: u32 = occupied.iter().map(|rect| rect.area()).sum();
+        assert_eq!(used + packer.free_area(), 100 * 100);
+
+        let mut packed_rects = Vec::new();
+        while let Some(rect) = packer.pack(Size::new(10, 10)) {
+            packed_rects.push(rect);
+        }
+
+        for (i, rect) in packed_rects.iter().enumerate() {
+            assert!(!occupied.iter().any(|taken| taken.intersects(rect)));
+            assert!(
+                !packed_rects[i + 1..]
+                    .iter()
+                    .any(|other| other.intersects(rect))
+            );
+        }
+    }
+
+    #[test]
+    fn test_pack_upright_never_rotates() {
+        let mut packer = MaxRectsPacker::new(Size::new(100, 40)).with_rotation(true);
+
+        assert!(packer.pack_upright(Size::new(40, 100)).is_none());
+        assert_eq!(
+            packer.pack(Size::new(40, 100)).unwrap(),
+            Rect::new(0, 0, 100, 40)
+        );
+    }
 }
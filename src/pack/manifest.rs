@@ -15,7 +15,7 @@ pub struct AtlasManifest {
 /// Information about a single sprite in the atlas
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpriteInfo {
-    /// The name of the sprite (usually filename without extension)
+    /// The name of the sprite (its path relative to the input)
     pub name: String,
     /// Rectangle defining the sprite's position and size in the atlas
     pub rect: Rect,
@@ -25,14 +25,34 @@ pub struct SpriteInfo {
     pub trimmed: bool,
     /// If trimmed, the rectangle of the original sprite that was kept
     pub sprite_source_size: Option<Rect>,
-    /// Which atlas page this sprite is on
+    /// Whether the sprite was rotated 90 degrees clockwise in the atlas
+    #[serde(default)]
+    pub rotated: bool,
+    /// Which atlas page this sprite is on, counted within its pack group
     pub page_index: usize,
+    /// The pack group whose pages this sprite is on, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// The nine-slice center, relative to the sprite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slice_center: Option<Rect>,
+}
+
+impl SpriteInfo {
+    /// Whether the sprite is on the given page
+    pub fn is_on(&self, page: &PageInfo) -> bool {
+        self.group == page.group && self.page_index == page.index
+    }
 }
 
 /// Information about an atlas page
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PageInfo {
+    /// The page's number within its pack group, as in its file name
     pub index: usize,
+    /// The pack group the page belongs to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
     pub filename: String,
     pub size: Size,
     pub sprite_count: usize,
@@ -54,12 +74,91 @@ impl AtlasManifest {
         self.sprites.insert(sprite_info.name.clone(), sprite_info);
     }
 
-    #[allow(dead_code)]
     pub fn add_page(&mut self, page_info: PageInfo) {
         self.pages.push(page_info);
     }
 
-    #[allow(dead_code)]
+    /// The page of a pack group with the given index
+    pub fn page(&self, group: Option<&str>, index: usize) -> Option<&PageInfo> {
+        self.pages
+            .iter()
+            .find(|page| page.group.as_deref() == group && page.index == index)
+    }
+
+    /// A copy with only the named sprites that are on pages of `group`, and
+    /// the pages they are on
+    pub fn subset<'a>(
+        &self,
+        group: Option<&str>,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let mut subset = Self {
+            input_name: self.input_name.clone(),
+            sprites: HashMap::new(),
+            pages: Vec::new(),
+            created_at: self.created_at.clone(),
+            options_hash: self.options_hash.clone(),
+        };
+
+        for name in names {
+            if let Some(sprite) = self.sprites.get(name)
+                && sprite.group.as_deref() == group
+            {
+                subset.add_sprite(sprite.clone());
+            }
+        }
+
+        for page in &self.pages {
+            if subset.sprites.values().any(|s| s.is_on(page)) {
+                subset.add_page(page.clone());
+            }
+        }
+
+        subset
+    }
+
+    /// Layer a newer manifest on top of this one. Pages with the same file name
+    /// are replaced along with the sprites on them, and so are sprites that
+    /// were packed again.
+    pub fn merge(mut self, newer: AtlasManifest) -> AtlasManifest {
+        let replaced_pages: Vec<PageInfo> = self
+            .pages
+            .iter()
+            .filter(|page| newer.pages.iter().any(|p| p.filename == page.filename))
+            .cloned()
+            .collect();
+
+        self.pages
+            .retain(|page| !newer.pages.iter().any(|p| p.filename == page.filename));
+        self.sprites.retain(|name, sprite| {
+            !replaced_pages.iter().any(|page| sprite.is_on(page))
+                && !newer.sprites.contains_key(name)
+        });
+
+        for page in &mut self.pages {
+            page.sprite_count = self
+                .sprites
+                .values()
+                .filter(|sprite| sprite.is_on(page))
+                .count();
+        }
+
+        for page in newer.pages {
+            self.add_page(page);
+        }
+
+        for sprite in newer.sprites.into_values() {
+            self.add_sprite(sprite);
+        }
+
+        AtlasManifest {
+            input_name: newer.input_name,
+            created_at: newer.created_at,
+            options_hash: newer.options_hash,
+            ..self
+        }
+    }
+
     pub fn set_options_hash(&mut self, hash: String) {
         self.options_hash = hash;
     }
@@ -111,6 +210,10 @@ impl AtlasManifest {
                 ));
             }
 
+            if sprite.rotated {
+                code.push_str("        rotated = true,\n");
+            }
+
             code.push_str("    },\n");
         }
 
@@ -132,6 +235,7 @@ impl AtlasManifest {
         code.push_str("    readonly sourceSize: Vector2;\n");
         code.push_str("    readonly spriteSourceOffset?: Vector2;\n");
         code.push_str("    readonly trimmed: boolean;\n");
+        code.push_str("    readonly rotated?: boolean;\n");
         code.push_str("}\n\n");
 
         code.push_str("export type Atlas = {\n");
@@ -166,6 +270,9 @@ impl AtlasManifest {
             }
 
             code.push_str(&format!("        trimmed: {},\n", sprite.trimmed));
+            if sprite.rotated {
+                code.push_str("        rotated: true,\n");
+            }
             code.push_str("    },\n");
         }
         code.push_str("};\n\n");
@@ -194,18 +301,22 @@ mod tests {
         let mut manifest = AtlasManifest::new("test_input".to_string());
 
         let sprite_info = SpriteInfo {
-            name: "test_sprite".to_string(),
+            name: "test/sprite.png".to_string(),
             rect: Rect::new(0, 0, 64, 64),
             source_size: Size::new(64, 64),
             trimmed: false,
             sprite_source_size: None,
+            rotated: false,
             page_index: 0,
+            group: None,
+            slice_center: None,
         };
 
         manifest.add_sprite(sprite_info);
 
         let page_info = PageInfo {
             index: 0,
+            group: None,
             filename: "test_input-sheet-0.png".to_string(),
             size: Size::new(512, 512),
             sprite_count: 1,
@@ -229,7 +340,7 @@ mod tests {
         let luau = manifest.generate_luau(Some(123456));
 
         assert!(luau.contains("rbxassetid://123456"));
-        assert!(luau.contains("test_sprite"));
+        assert!(luau.contains("[\"test/sprite.png\"]"));
         assert!(luau.contains("imageRectOffset = Vector2.new(0, 0)"));
         assert!(luau.contains("imageRectSize = Vector2.new(64, 64)"));
         assert!(luau.contains("sourceSize = Vector2.new(64, 64)"));
@@ -243,7 +354,7 @@ mod tests {
 
         assert!(typescript.contains("export interface Sprite"));
         assert!(typescript.contains("export type Atlas"));
-        assert!(typescript.contains("test_sprite"));
+        assert!(typescript.contains("\"test/sprite.png\""));
         assert!(typescript.contains("imageRectOffset: new Vector2(0, 0)"));
         assert!(typescript.contains("imageRectSize: new Vector2(64, 64)"));
         assert!(typescript.contains("sourceSize: new Vector2(64, 64)"));
@@ -260,4 +371,63 @@ mod tests {
         assert_eq!(manifest.sprite_count(), deserialized.sprite_count());
         assert_eq!(manifest.page_count(), deserialized.page_count());
     }
+
+    #[test]
+    fn test_merge_replaces_repacked_pages() {
+        let mut previous = create_test_manifest();
+        previous.add_sprite(SpriteInfo {
+            name: "test/other.png".to_string(),
+            rect: Rect::new(0, 0, 32, 32),
+            source_size: Size::new(32, 32),
+            trimmed: false,
+            sprite_source_size: None,
+            rotated: false,
+            page_index: 1,
+            group: None,
+            slice_center: None,
+        });
+        previous.add_page(PageInfo {
+            index: 1,
+            group: None,
+            filename: "test_input-sheet-1.png".to_string(),
+            size: Size::new(32, 32),
+            sprite_count: 1,
+        });
+
+        // Page 0 is packed again with a new sprite on it
+        let mut newer = AtlasManifest::new("test_input".to_string());
+        newer.add_sprite(SpriteInfo {
+            name: "test/new.png".to_string(),
+            rect: Rect::new(0, 0, 16, 16),
+            source_size: Size::new(16, 16),
+            trimmed: false,
+            sprite_source_size: None,
+            rotated: false,
+            page_index: 0,
+            group: None,
+            slice_center: None,
+        });
+        newer.add_page(PageInfo {
+            index: 0,
+            group: None,
+            filename: "test_input-sheet-0.png".to_string(),
+            size: Size::new(16, 16),
+            sprite_count: 1,
+        });
+        newer.set_options_hash("abc".to_string());
+
+        let merged = previous.merge(newer);
+
+        assert_eq!(merged.page_count(), 2);
+        assert_eq!(merged.sprite_count(), 2);
+        assert!(!merged.sprites.contains_key("test/sprite.png"));
+        assert_eq!(merged.sprites["test/other.png"].page_index, 1);
+
+        let new_page = merged.sprites["test/new.png"].page_index;
+        assert_eq!(
+            merged.page(None, new_page).unwrap().filename,
+            "test_input-sheet-0.png"
+        );
+        assert_eq!(merged.options_hash, "abc");
+    }
 }
@@ -1,26 +1,56 @@
 use crate::{
     asset::Asset,
-    config::{PackOptions, PackSort},
+    config::{
+        PackAlgorithm, PackFreeRectChoice, PackHeuristic, PackOptions, PackOverflow, PackSort,
+        PackSplitRule,
+    },
 };
+use algorithm::{FreeRectChoice, MaxRectsHeuristic, PackerHeuristic, RectPacker, SplitRule};
 use anyhow::{Context, Result, bail};
 use image::RgbaImage;
-use std::collections::HashMap;
+use sequence::SequencePattern;
+use std::collections::{BTreeMap, HashMap};
 
 pub mod algorithm;
 pub mod manifest;
+pub mod preview;
 pub mod rect;
+pub mod sequence;
 
-pub use manifest::{AtlasManifest, SpriteInfo};
+pub use manifest::{AtlasManifest, PageInfo, SpriteInfo};
 pub use rect::{Rect, Size};
 
 /// A sprite to be packed into an atlas
 #[derive(Debug, Clone)]
 pub struct Sprite {
+    /// The sprite's path relative to the input
     pub name: String,
     pub data: Vec<u8>,
     pub size: Size,
     #[allow(dead_code)]
     pub hash: String,
+    /// If trimmed, the rectangle reported by trimming
+    pub sprite_source_size: Option<Rect>,
+    /// The flipbook sequence this sprite is a frame of, whose frames share a page
+    pub sequence: Option<String>,
+    /// Sliced sprites are packed as they are, since trimming or rotating
+    /// them would move their slice center
+    pub slice_center: Option<Rect>,
+}
+
+impl Sprite {
+    /// A copy of the sprite without its image data, for trial layouts
+    fn shape(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            data: Vec::new(),
+            size: self.size,
+            hash: self.hash.clone(),
+            sprite_source_size: self.sprite_source_size,
+            sequence: self.sequence.clone(),
+            slice_center: self.slice_center,
+        }
+    }
 }
 
 /// Result of packing sprites into atlases
@@ -28,16 +58,24 @@ pub struct Sprite {
 pub struct PackResult {
     pub atlases: Vec<Atlas>,
     pub manifest: AtlasManifest,
+    /// Names of sprites that could not be packed and should be uploaded on their own
+    pub overflow: Vec<String>,
 }
 
 /// A single atlas page containing packed sprites
 #[derive(Debug)]
 pub struct Atlas {
+    /// The page's number within its pack group
     pub page_index: usize,
+    /// The pack group the page belongs to, if any
+    pub group: Option<String>,
     pub image_data: Vec<u8>,
-    #[allow(dead_code)]
     pub size: Size,
     pub sprites: Vec<PackedSprite>,
+    /// The padding the page's sprites were packed with
+    pub padding: u32,
+    /// How much of the page the packer filled, padding included (0.0 to 1.0)
+    pub occupancy: f64,
 }
 
 /// A sprite that has been placed in an atlas
@@ -47,6 +85,100 @@ pub struct PackedSprite {
     pub rect: Rect,
     pub trimmed: bool,
     pub sprite_source_size: Option<Rect>,
+    /// Whether the sprite was rotated 90 degrees clockwise to fit in the atlas
+    pub rotated: bool,
+}
+
+/// The file name an atlas page is uploaded under
+pub fn page_filename(sheet_name: &str, page_index: usize) -> String {
+    format!("{sheet_name}-sheet-{page_index}.png")
+}
+
+/// The name a group's pages are prefixed with, `None` being the input's own pages
+pub fn sheet_name(input_name: &str, group: Option<&str>) -> String {
+    match group {
+        Some(group) => format!("{input_name}-{group}"),
+        None => input_name.to_string(),
+    }
+}
+
+/// Group the frames of each sequence together at the position of its first
+/// frame, leaving every other sprite on its own
+fn bundle_sequences(sprites: Vec<Sprite>) -> Vec<Vec<Sprite>> {
+    let mut bundles: Vec<Vec<Sprite>> = Vec::new();
+    let mut sequence_bundles = HashMap::<String, usize>::new();
+
+    for sprite in sprites {
+        match &sprite.sequence {
+            Some(sequence) => match sequence_bundles.get(sequence) {
+                Some(&index) => bundles[index].push(sprite),
+                None => {
+                    sequence_bundles.insert(sequence.clone(), bundles.len());
+                    bundles.push(vec![sprite]);
+                }
+            },
+            None => bundles.push(vec![sprite]),
+        }
+    }
+
+    bundles
+}
+
+/// Combine the results of packing each group, tagging their pages with the
+/// group they belong to. Pages keep the index they are named after.
+fn merge_results(input_name: &str, results: Vec<(Option<&str>, PackResult)>) -> PackResult {
+    let mut merged = PackResult {
+        atlases: Vec::new(),
+        manifest: AtlasManifest::new(input_name.to_string()),
+        overflow: Vec::new(),
+    };
+
+    for (group, result) in results {
+        let group = group.map(str::to_string);
+
+        for mut page in result.manifest.pages {
+            page.group = group.clone();
+            merged.manifest.add_page(page);
+        }
+
+        for mut atlas in result.atlases {
+            atlas.group = group.clone();
+            merged.atlases.push(atlas);
+        }
+
+        for mut sprite in result.manifest.sprites.into_values() {
+            sprite.group = group.clone();
+            merged.manifest.add_sprite(sprite);
+        }
+
+        merged.overflow.extend(result.overflow);
+    }
+
+    merged
+}
+
+/// The layout of a single atlas page before it is rendered
+#[derive(Debug)]
+struct PageLayout {
+    size: Size,
+    sprites: Vec<PackedSprite>,
+    occupancy: f64,
+}
+
+/// Whether `candidate` is a better layout than `best`: fewer pages first,
+/// then fuller pages towards the front
+fn is_better_layout(candidate: &[PageLayout], best: &[PageLayout]) -> bool {
+    if candidate.len() != best.len() {
+        return candidate.len() < best.len();
+    }
+
+    for (a, b) in candidate.iter().zip(best) {
+        if a.occupancy != b.occupancy {
+            return a.occupancy > b.occupancy;
+        }
+    }
+
+    false
 }
 
 /// Main packing orchestrator
@@ -60,18 +192,197 @@ impl Packer {
     }
 
     /// Pack a collection of assets into atlases
+    ///
+    /// Every pack group gets its own pages, named after the group.
     pub fn pack_assets(&self, assets: &[Asset], input_name: &str) -> Result<PackResult> {
+        let previous = AtlasManifest::new(input_name.to_string());
+        self.pack_assets_after(assets, &previous, input_name)
+    }
+
+    /// Pack assets onto new pages numbered after the pages of `previous`
+    ///
+    /// The previous pages are left as they are, so the sprites on them keep
+    /// their place and their files aren't replaced by the new pages.
+    pub fn pack_assets_after(
+        &self,
+        assets: &[Asset],
+        previous: &AtlasManifest,
+        input_name: &str,
+    ) -> Result<PackResult> {
+        if !self.options.enabled {
+            bail!("Packing is not enabled for input '{}'", input_name);
+        }
+
+        let mut results = Vec::new();
+
+        for (group, packer) in self.group_packers() {
+            let group_assets = self.assets_in_group(assets, group);
+            if group_assets.is_empty() {
+                continue;
+            }
+
+            let first_index = previous
+                .pages
+                .iter()
+                .filter(|page| page.group.as_deref() == group)
+                .map(|page| page.index + 1)
+                .max()
+                .unwrap_or(0);
+
+            let sheet_name = sheet_name(input_name, group);
+            let result = packer.pack_group(&group_assets, &sheet_name, first_index)?;
+            results.push((group, result));
+        }
+
+        let mut result = merge_results(input_name, results);
+        result.manifest.set_options_hash(self.options_hash());
+        Ok(result)
+    }
+
+    /// Pack new sprites around the sprites kept from a previous layout
+    ///
+    /// `kept` are the unchanged assets from `previous`, which stay at their
+    /// previous rects. New sprites fill the free space on previous pages first
+    /// and only the pages that received new sprites are rendered. Whatever
+    /// doesn't fit goes onto new pages after the previous ones.
+    pub fn pack_incremental(
+        &self,
+        assets: &[Asset],
+        kept: &[Asset],
+        previous: &AtlasManifest,
+        input_name: &str,
+    ) -> Result<PackResult> {
         if !self.options.enabled {
             bail!("Packing is not enabled for input '{}'", input_name);
         }
 
+        let mut results = Vec::new();
+
+        for (group, packer) in self.group_packers() {
+            // Groups without new sprites keep their pages as they are
+            let group_assets = self.assets_in_group(assets, group);
+            if group_assets.is_empty() {
+                continue;
+            }
+
+            let group_kept = self.assets_in_group(kept, group);
+            let group_previous =
+                previous.subset(group, group_kept.iter().map(|asset| asset.path.as_str()));
+
+            let sheet_name = sheet_name(input_name, group);
+            let result = packer.pack_group_incremental(
+                &group_assets,
+                &group_kept,
+                &group_previous,
+                &sheet_name,
+            )?;
+            results.push((group, result));
+        }
+
+        let mut result = merge_results(input_name, results);
+        result.manifest.set_options_hash(self.options_hash());
+        Ok(result)
+    }
+
+    /// A hash of the options that affect the layout, so consumers of the
+    /// manifest can tell when atlases were packed with different settings
+    pub fn options_hash(&self) -> String {
+        let mut hasher = blake3::Hasher::new();
+        self.hash_layout_options(&mut hasher);
+
+        for (name, group) in &self.options.groups {
+            hasher.update(name.as_bytes());
+            hasher.update(group.glob.to_string().as_bytes());
+            Packer::new(group.resolve(&self.options)).hash_layout_options(&mut hasher);
+        }
+
+        hasher.finalize().to_string()
+    }
+
+    /// Feed the options of a single group that affect where sprites end up to
+    /// `hasher`, leaving out the ones that only affect what happens to the pages
+    fn hash_layout_options(&self, hasher: &mut blake3::Hasher) {
+        let options = &self.options;
+
+        for value in [
+            options.max_size.0,
+            options.max_size.1,
+            options.padding,
+            options.extrude,
+            options.page_limit.unwrap_or(0),
+            options.algorithm.clone() as u32,
+            options.heuristic.clone() as u32,
+            options.free_rect_choice.clone() as u32,
+            options.split_rule.clone() as u32,
+            options.sort.clone() as u32,
+        ] {
+            hasher.update(&value.to_le_bytes());
+        }
+
+        hasher.update(&[
+            options.power_of_two as u8,
+            options.shrink_to_fit as u8,
+            options.allow_trim as u8,
+            options.dedupe as u8,
+            options.allow_rotation as u8,
+            options.incremental as u8,
+            options.sequences as u8,
+        ]);
+
+        if options.sequences {
+            hasher.update(options.sequence_pattern.as_bytes());
+        }
+    }
+
+    /// The pack group a sprite belongs to: the first one in name order whose
+    /// glob matches it, or `None` for the input's own pages
+    fn group_of(&self, name: &str) -> Option<&str> {
+        self.options
+            .groups
+            .iter()
+            .find(|(_, group)| group.glob.is_match(name))
+            .map(|(group_name, _)| group_name.as_str())
+    }
+
+    fn assets_in_group<'a>(&self, assets: &'a [Asset], group: Option<&str>) -> Vec<&'a Asset> {
+        assets
+            .iter()
+            .filter(|asset| self.group_of(asset.path.as_str()) == group)
+            .collect()
+    }
+
+    /// A packer for each pack group with its overrides applied, followed by the input's own
+    fn group_packers(&self) -> Vec<(Option<&str>, Packer)> {
+        let mut packers: Vec<_> = self
+            .options
+            .groups
+            .iter()
+            .map(|(name, group)| {
+                let options = group.resolve(&self.options);
+                (Some(name.as_str()), Packer::new(options))
+            })
+            .collect();
+
+        packers.push((None, Packer::new(self.options.clone())));
+        packers
+    }
+
+    /// Pack the assets of a single group onto pages named after `sheet_name`,
+    /// numbered from `first_index`
+    fn pack_group(
+        &self,
+        assets: &[&Asset],
+        sheet_name: &str,
+        first_index: usize,
+    ) -> Result<PackResult> {
         // Convert assets to sprites
         let sprites = self.assets_to_sprites(assets)?;
 
         if sprites.is_empty() {
             return Ok(PackResult {
                 atlases: Vec::new(),
-                manifest: AtlasManifest::new(input_name.to_string()),
+                manifest: AtlasManifest::new(sheet_name.to_string()),
+                overflow: Vec::new(),
             });
         }
 
@@ -80,32 +391,167 @@ impl Packer {
         self.sort_sprites(&mut sorted_sprites);
 
         // Validate sprite sizes
-        self.validate_sprite_sizes(&sorted_sprites)?;
+        let (mut sorted_sprites, mut overflow) = self.split_oversized_sprites(sorted_sprites)?;
+
+        // Trim once up front so sprites that spill onto later pages keep their trim info
+        for sprite in &mut sorted_sprites {
+            sprite.sprite_source_size = self.trim_sprite(sprite);
+        }
 
-        // Pack sprites into pages
-        let atlases = self.pack_sprites_to_atlases(sorted_sprites)?;
+        // Lay out sprites into pages
+        let (heuristic, mut pages) = self.layout_best(sorted_sprites, &mut overflow)?;
 
         // Check page limit
-        if let Some(limit) = self.options.page_limit
-            && atlases.len() > limit as usize
-        {
-            bail!(
-                "Packing would require {} pages but limit is {}. Consider increasing max_size or page_limit.",
-                atlases.len(),
-                limit
+        self.apply_page_limit(&mut pages, 0, &mut overflow)?;
+
+        // Render pages into atlases
+        let atlases = self.render_pages(pages, heuristic, first_index)?;
+
+        // Generate manifest
+        let manifest = self.create_manifest(&atlases, sheet_name)?;
+
+        Ok(PackResult {
+            atlases,
+            manifest,
+            overflow,
+        })
+    }
+
+    /// Incrementally pack the assets of a single group, see [`Packer::pack_incremental`]
+    fn pack_group_incremental(
+        &self,
+        assets: &[&Asset],
+        kept: &[&Asset],
+        previous: &AtlasManifest,
+        sheet_name: &str,
+    ) -> Result<PackResult> {
+        let max_size = self.max_page_size();
+
+        let mut new_sprites = self.assets_to_sprites(assets)?;
+        for sprite in &mut new_sprites {
+            sprite.sprite_source_size = self.trim_sprite(sprite);
+        }
+
+        let mut previous_pages: BTreeMap<usize, Vec<PackedSprite>> = previous
+            .pages
+            .iter()
+            .map(|page| (page.index, Vec::new()))
+            .collect();
+
+        for mut sprite in self.assets_to_sprites(kept)? {
+            sprite.sprite_source_size = self.trim_sprite(&mut sprite);
+
+            let Some(info) = previous.sprites.get(&sprite.name) else {
+                new_sprites.push(sprite);
+                continue;
+            };
+
+            // Sprites only keep their place if they still look the way they were packed
+            let placed_size = if info.rotated {
+                sprite.size.rotated()
+            } else {
+                sprite.size
+            };
+
+            if placed_size != info.rect.size()
+                || info.rect.right() > max_size.width
+                || info.rect.bottom() > max_size.height
+            {
+                log::debug!(
+                    "Sprite '{}' no longer matches its previous placement and will be repacked",
+                    sprite.name
+                );
+                new_sprites.push(sprite);
+                continue;
+            }
+
+            previous_pages
+                .entry(info.page_index)
+                .or_default()
+                .push(PackedSprite {
+                    trimmed: sprite.sprite_source_size.is_some(),
+                    sprite_source_size: sprite.sprite_source_size,
+                    sprite,
+                    rect: info.rect,
+                    rotated: info.rotated,
+                });
+        }
+
+        self.sort_sprites(&mut new_sprites);
+        let (remaining, mut overflow) = self.split_oversized_sprites(new_sprites)?;
+
+        let existing_pages = previous_pages.len();
+        let next_index = previous_pages
+            .keys()
+            .next_back()
+            .map_or(0, |index| index + 1);
+
+        // Every heuristic fills the previous pages and lays out what is left on
+        // new ones, and the one with the fewest and fullest changed pages wins
+        type Candidate = (PackerHeuristic, Vec<usize>, Vec<PageLayout>, Vec<Sprite>);
+        let mut best: Option<Candidate> = None;
+
+        for heuristic in self.heuristics_to_try() {
+            let (changed_indices, mut pages, remaining) =
+                self.fill_previous_pages(&previous_pages, remaining.clone(), heuristic);
+
+            let (new_pages, unplaced) = if remaining.is_empty() {
+                (Vec::new(), Vec::new())
+            } else {
+                self.layout_pages(remaining, heuristic)?
+            };
+            pages.extend(new_pages);
+
+            log::debug!(
+                "Heuristic {:?} changed {} page(s) and added {} page(s)",
+                heuristic,
+                changed_indices.len(),
+                pages.len() - changed_indices.len()
             );
+
+            let is_better = match &best {
+                Some((_, _, best_pages, _)) => is_better_layout(&pages, best_pages),
+                None => true,
+            };
+
+            if is_better {
+                best = Some((heuristic, changed_indices, pages, unplaced));
+            }
         }
 
-        // Generate manifest
-        let manifest = self.create_manifest(&atlases, input_name)?;
+        let (heuristic, changed_indices, mut pages, unplaced) =
+            best.context("No packing heuristics to try")?;
+        self.overflow_unplaced(unplaced, &mut overflow);
+
+        let mut new_pages = pages.split_off(changed_indices.len());
+        self.apply_page_limit(&mut new_pages, existing_pages, &mut overflow)?;
 
-        Ok(PackResult { atlases, manifest })
+        let mut atlases = changed_indices
+            .into_iter()
+            .zip(pages)
+            .map(|(page_index, page)| self.render_page(page_index, self.fit_page(page)))
+            .collect::<Result<Vec<_>>>()?;
+        atlases.extend(self.render_pages(new_pages, heuristic, next_index)?);
+
+        let manifest = self.create_manifest(&atlases, sheet_name)?;
+
+        Ok(PackResult {
+            atlases,
+            manifest,
+            overflow,
+        })
     }
 
-    fn assets_to_sprites(&self, assets: &[Asset]) -> Result<Vec<Sprite>> {
+    fn assets_to_sprites(&self, assets: &[&Asset]) -> Result<Vec<Sprite>> {
         let mut sprites = Vec::new();
         let mut seen_hashes = HashMap::new();
 
+        let sequence_pattern = self
+            .options
+            .sequences
+            .then(|| SequencePattern::new(&self.options.sequence_pattern))
+            .transpose()?;
+
         for asset in assets {
             // Only pack image assets
             if !matches!(asset.ty, crate::asset::AssetType::Image(_)) {
@@ -121,7 +567,8 @@ impl Packer {
                 height: image.height(),
             };
 
-            let name = asset.path.file_stem().unwrap_or("unknown").to_string();
+            // The full input-relative path keeps sprites in different folders distinct
+            let name = asset.path.to_string();
 
             // Handle deduplication
             if self.options.dedupe {
@@ -141,6 +588,12 @@ impl Packer {
                 data: asset.data.to_vec(),
                 size,
                 hash: asset.hash.clone(),
+                sprite_source_size: None,
+                sequence: sequence_pattern
+                    .as_ref()
+                    .and_then(|pattern| pattern.frame_of(asset.path.as_str()))
+                    .map(|frame| frame.sequence),
+                slice_center: asset.slice_center,
             });
         }
 
@@ -168,49 +621,337 @@ impl Packer {
         });
     }
 
-    fn validate_sprite_sizes(&self, sprites: &[Sprite]) -> Result<()> {
-        let max_width = self.options.max_size.0;
-        let max_height = self.options.max_size.1;
+    /// Split off sprites that can never fit on a page, either failing or
+    /// returning their names depending on the overflow policy
+    fn split_oversized_sprites(&self, sprites: Vec<Sprite>) -> Result<(Vec<Sprite>, Vec<String>)> {
+        let max_size = self.max_page_size();
+        let padding = 2 * self.options.padding;
+
+        let mut fitting = Vec::with_capacity(sprites.len());
+        let mut oversized = Vec::new();
 
         for sprite in sprites {
-            if sprite.size.width > max_width || sprite.size.height > max_height {
-                bail!(
-                    "Sprite '{}' ({}x{}) exceeds maximum atlas size ({}x{}). Consider increasing max_size or excluding this sprite from packing.",
+            let required_size = Size {
+                width: sprite.size.width + padding,
+                height: sprite.size.height + padding,
+            };
+
+            let fits = required_size.fits_in(max_size)
+                || (self.can_rotate(&sprite) && required_size.rotated().fits_in(max_size));
+
+            if fits {
+                fitting.push(sprite);
+                continue;
+            }
+
+            match self.options.overflow {
+                PackOverflow::Error => bail!(
+                    "Sprite '{}' ({}x{}) with {}px padding exceeds maximum atlas size ({}x{}). Consider increasing max_size or excluding this sprite from packing.",
                     sprite.name,
                     sprite.size.width,
                     sprite.size.height,
-                    max_width,
-                    max_height
+                    self.options.padding,
+                    max_size.width,
+                    max_size.height
+                ),
+                PackOverflow::UploadIndividually => {
+                    log::warn!(
+                        "Sprite '{}' ({}x{}) exceeds maximum atlas size ({}x{}), it will be uploaded individually",
+                        sprite.name,
+                        sprite.size.width,
+                        sprite.size.height,
+                        max_size.width,
+                        max_size.height
+                    );
+                    oversized.push(sprite.name);
+                }
+            }
+        }
+
+        Ok((fitting, oversized))
+    }
+
+    /// Lay out sprites with every heuristic to try and keep the best layout,
+    /// adding the frames of sequences too large for a page to `overflow`
+    fn layout_best(
+        &self,
+        sprites: Vec<Sprite>,
+        overflow: &mut Vec<String>,
+    ) -> Result<(PackerHeuristic, Vec<PageLayout>)> {
+        let mut best_layout: Option<(PackerHeuristic, Vec<PageLayout>, Vec<Sprite>)> = None;
+
+        for heuristic in self.heuristics_to_try() {
+            let (layout, unplaced) = self.layout_pages(sprites.clone(), heuristic)?;
+
+            log::debug!(
+                "Heuristic {:?} produced {} page(s)",
+                heuristic,
+                layout.len()
+            );
+
+            let is_better = match &best_layout {
+                Some((_, best, _)) => is_better_layout(&layout, best),
+                None => true,
+            };
+
+            if is_better {
+                best_layout = Some((heuristic, layout, unplaced));
+            }
+        }
+
+        let (heuristic, layout, unplaced) = best_layout.context("No packing heuristics to try")?;
+        self.overflow_unplaced(unplaced, overflow);
+        Ok((heuristic, layout))
+    }
+
+    /// Add sprites that could not be laid out to `overflow`, warning about
+    /// each sequence among them
+    fn overflow_unplaced(&self, unplaced: Vec<Sprite>, overflow: &mut Vec<String>) {
+        let mut sequences: Vec<&str> = unplaced
+            .iter()
+            .filter_map(|sprite| sprite.sequence.as_deref())
+            .collect();
+        sequences.dedup();
+
+        for sequence in sequences {
+            log::warn!(
+                "The frames of sequence '{sequence}' do not fit on a single atlas page, they will be uploaded individually"
+            );
+        }
+
+        overflow.extend(unplaced.into_iter().map(|sprite| sprite.name));
+    }
+
+    /// Place sprites into the free space left on the previous pages, returning
+    /// the indices and layouts of the pages that gained sprites along with the
+    /// sprites that fit on none of them
+    fn fill_previous_pages(
+        &self,
+        previous_pages: &BTreeMap<usize, Vec<PackedSprite>>,
+        mut remaining: Vec<Sprite>,
+        heuristic: PackerHeuristic,
+    ) -> (Vec<usize>, Vec<PageLayout>, Vec<Sprite>) {
+        let max_size = self.max_page_size();
+        let mut changed_indices = Vec::new();
+        let mut changed_pages = Vec::new();
+
+        for (&page_index, sprites) in previous_pages {
+            if remaining.is_empty() {
+                break;
+            }
+
+            let mut packer =
+                algorithm::create_packer(heuristic, max_size, self.options.allow_rotation);
+
+            for packed in sprites {
+                packer.occupy(self.padded_rect(packed.rect, max_size));
+            }
+
+            let (placed, unplaced) = self.place_sprites(&mut packer, remaining);
+            remaining = unplaced;
+
+            if placed.is_empty() {
+                continue;
+            }
+
+            log::debug!(
+                "Placed {} new sprite(s) on existing atlas page {page_index}",
+                placed.len()
+            );
+
+            let mut sprites = sprites.clone();
+            sprites.extend(placed);
+            changed_indices.push(page_index);
+            changed_pages.push(PageLayout {
+                size: max_size,
+                sprites,
+                occupancy: packer.occupancy(),
+            });
+        }
+
+        (changed_indices, changed_pages, remaining)
+    }
+
+    /// Enforce the page limit on newly laid out pages, following the overflow policy
+    fn apply_page_limit(
+        &self,
+        pages: &mut Vec<PageLayout>,
+        existing_pages: usize,
+        overflow: &mut Vec<String>,
+    ) -> Result<()> {
+        let Some(limit) = self.options.page_limit else {
+            return Ok(());
+        };
+
+        let total_pages = existing_pages + pages.len();
+        if total_pages <= limit as usize {
+            return Ok(());
+        }
+
+        match self.options.overflow {
+            PackOverflow::Error => bail!(
+                "Packing would require {} pages but limit is {}. Consider increasing max_size or page_limit.",
+                total_pages,
+                limit
+            ),
+            PackOverflow::UploadIndividually => {
+                let dropped = pages.split_off((limit as usize).saturating_sub(existing_pages));
+                let dropped_sprites: Vec<String> = dropped
+                    .into_iter()
+                    .flat_map(|page| page.sprites)
+                    .map(|packed| packed.sprite.name)
+                    .collect();
+
+                log::warn!(
+                    "Packing would exceed the page limit of {limit}, {} sprite(s) will be uploaded individually",
+                    dropped_sprites.len()
                 );
+
+                overflow.extend(dropped_sprites);
             }
         }
 
         Ok(())
     }
 
-    fn pack_sprites_to_atlases(&self, sprites: Vec<Sprite>) -> Result<Vec<Atlas>> {
-        let mut atlases = Vec::new();
+    fn render_pages(
+        &self,
+        pages: Vec<PageLayout>,
+        heuristic: PackerHeuristic,
+        first_index: usize,
+    ) -> Result<Vec<Atlas>> {
+        pages
+            .into_iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let page = if self.options.shrink_to_fit {
+                    self.shrink_page(page, heuristic)
+                } else {
+                    page
+                };
+
+                self.render_page(first_index + i, page)
+            })
+            .collect()
+    }
+
+    fn render_page(&self, page_index: usize, page: PageLayout) -> Result<Atlas> {
+        let image_data = self.render_atlas(&page.sprites, page.size)?;
+
+        Ok(Atlas {
+            page_index,
+            group: None,
+            image_data,
+            size: page.size,
+            sprites: page.sprites,
+            padding: self.options.padding,
+            occupancy: page.occupancy,
+        })
+    }
+
+    /// The heuristics of the configured algorithm to lay out pages with, in
+    /// order of preference. Auto tries every one of them.
+    fn heuristics_to_try(&self) -> Vec<PackerHeuristic> {
+        match self.options.algorithm {
+            PackAlgorithm::MaxRects => {
+                let heuristics: &[MaxRectsHeuristic] = match self.options.heuristic {
+                    PackHeuristic::BestShortSideFit => &[MaxRectsHeuristic::BestShortSideFit],
+                    PackHeuristic::BestLongSideFit => &[MaxRectsHeuristic::BestLongSideFit],
+                    PackHeuristic::BestAreaFit => &[MaxRectsHeuristic::BestAreaFit],
+                    PackHeuristic::BottomLeft => &[MaxRectsHeuristic::BottomLeft],
+                    PackHeuristic::ContactPoint => &[MaxRectsHeuristic::ContactPoint],
+                    PackHeuristic::Auto => MaxRectsHeuristic::ALL,
+                };
+
+                heuristics
+                    .iter()
+                    .map(|&heuristic| PackerHeuristic::MaxRects(heuristic))
+                    .collect()
+            }
+            PackAlgorithm::Guillotine => {
+                let choices: &[FreeRectChoice] = match self.options.free_rect_choice {
+                    PackFreeRectChoice::BestAreaFit => &[FreeRectChoice::BestAreaFit],
+                    PackFreeRectChoice::BestShortSideFit => &[FreeRectChoice::BestShortSideFit],
+                    PackFreeRectChoice::BestLongSideFit => &[FreeRectChoice::BestLongSideFit],
+                    PackFreeRectChoice::Auto => FreeRectChoice::ALL,
+                };
+
+                let splits: &[SplitRule] = match self.options.split_rule {
+                    PackSplitRule::ShorterLeftoverAxis => &[SplitRule::ShorterLeftoverAxis],
+                    PackSplitRule::LongerLeftoverAxis => &[SplitRule::LongerLeftoverAxis],
+                    PackSplitRule::ShorterAxis => &[SplitRule::ShorterAxis],
+                    PackSplitRule::LongerAxis => &[SplitRule::LongerAxis],
+                    PackSplitRule::MinimizeArea => &[SplitRule::MinimizeArea],
+                    PackSplitRule::MaximizeArea => &[SplitRule::MaximizeArea],
+                    PackSplitRule::Auto => SplitRule::ALL,
+                };
+
+                choices
+                    .iter()
+                    .flat_map(|&choice| {
+                        splits
+                            .iter()
+                            .map(move |&split| PackerHeuristic::Guillotine(choice, split))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Lay out sprites onto as many pages as they need, returning the frames
+    /// of sequences that don't fit on a page when they can be uploaded on
+    /// their own
+    fn layout_pages(
+        &self,
+        sprites: Vec<Sprite>,
+        heuristic: PackerHeuristic,
+    ) -> Result<(Vec<PageLayout>, Vec<Sprite>)> {
+        let mut pages = Vec::new();
+        let mut unplaced = Vec::new();
         let mut remaining_sprites = sprites;
 
         while !remaining_sprites.is_empty() {
-            let page_index = atlases.len();
-            let (atlas, unpacked_sprites) =
-                self.pack_single_atlas(remaining_sprites, page_index)?;
-            atlases.push(atlas);
+            let (page, unpacked_sprites) =
+                self.layout_single_page(remaining_sprites, heuristic, self.max_page_size());
+
+            if page.sprites.is_empty() {
+                let sprite = &unpacked_sprites[0];
+
+                if let Some(sequence) = sprite.sequence.clone() {
+                    if matches!(self.options.overflow, PackOverflow::Error) {
+                        bail!(
+                            "The frames of sequence '{sequence}' do not fit on a single atlas page. Consider increasing max_size or splitting the sequence."
+                        );
+                    }
+
+                    let (frames, rest): (Vec<_>, Vec<_>) = unpacked_sprites
+                        .into_iter()
+                        .partition(|sprite| sprite.sequence.as_ref() == Some(&sequence));
+
+                    unplaced.extend(frames);
+                    remaining_sprites = rest;
+                    continue;
+                }
+
+                bail!(
+                    "Sprite '{}' ({}x{}) does not fit on an empty atlas page with {}px padding. Consider increasing max_size or reducing padding.",
+                    sprite.name,
+                    sprite.size.width,
+                    sprite.size.height,
+                    self.options.padding
+                );
+            }
+
+            pages.push(page);
             remaining_sprites = unpacked_sprites;
         }
 
-        Ok(atlases)
+        Ok((pages, unplaced))
     }
 
-    fn pack_single_atlas(
-        &self,
-        sprites: Vec<Sprite>,
-        page_index: usize,
-    ) -> Result<(Atlas, Vec<Sprite>)> {
-        use algorithm::MaxRectsPacker;
-
-        let atlas_size = if self.options.power_of_two {
+    /// The size every page is laid out at before it is shrunk to fit
+    fn max_page_size(&self) -> Size {
+        if self.options.power_of_two {
             // Find the next power of two that fits our max size
             let width = self.options.max_size.0.next_power_of_two();
             let height = self.options.max_size.1.next_power_of_two();
@@ -220,59 +961,271 @@ impl Packer {
                 width: self.options.max_size.0,
                 height: self.options.max_size.1,
             }
+        }
+    }
+
+    /// Find the smallest page that still fits every sprite already assigned to `page`
+    fn shrink_page(&self, page: PageLayout, heuristic: PackerHeuristic) -> PageLayout {
+        // Trial layouts only need each sprite's size, the image data is moved
+        // over to whichever layout is chosen
+        let sprites: Vec<Sprite> = page.sprites.iter().map(|p| p.sprite.shape()).collect();
+        let padding = 2 * self.options.padding;
+
+        // Every sprite has to fit on its own, in whichever orientation is narrower
+        let mut min_size = Size::new(1, 1);
+        let mut used_area = 0;
+        for sprite in &sprites {
+            let size = Size::new(sprite.size.width + padding, sprite.size.height + padding);
+            let (width, height) = if self.can_rotate(sprite) {
+                (size.min_side(), size.min_side())
+            } else {
+                (size.width, size.height)
+            };
+
+            min_size.width = min_size.width.max(width);
+            min_size.height = min_size.height.max(height);
+            used_area += size.area();
+        }
+
+        let widths = self.candidate_sides(min_size.width, page.size.width, true);
+        let heights = self.candidate_sides(min_size.height, page.size.height, false);
+
+        let mut best: Option<PageLayout> = None;
+
+        for width in widths {
+            // Binary search for the shortest height that fits everything at this width
+            let (mut low, mut high) = (0, heights.len());
+            let mut found = None;
+
+            while low < high {
+                let mid = (low + high) / 2;
+                let size = Size::new(width, heights[mid]);
+
+                let layout = (size.area() >= used_area)
+                    .then(|| self.layout_single_page(sprites.clone(), heuristic, size))
+                    .filter(|(_, unpacked)| unpacked.is_empty());
+
+                match layout {
+                    Some((layout, _)) => {
+                        found = Some(layout);
+                        high = mid;
+                    }
+                    None => low = mid + 1,
+                }
+            }
+
+            let best_area = best
+                .as_ref()
+                .map_or(page.size.area(), |best| best.size.area());
+            if let Some(layout) = found
+                && layout.size.area() < best_area
+            {
+                best = Some(layout);
+            }
+        }
+
+        let Some(mut best) = best else {
+            return page;
         };
 
-        let mut packer = MaxRectsPacker::new(atlas_size);
+        let mut data: HashMap<String, Vec<u8>> = page
+            .sprites
+            .into_iter()
+            .map(|packed| (packed.sprite.name, packed.sprite.data))
+            .collect();
+
+        for packed in &mut best.sprites {
+            packed.sprite.data = data.remove(&packed.sprite.name).unwrap_or_default();
+        }
+
+        log::debug!(
+            "Shrunk atlas page to {}x{}",
+            best.size.width,
+            best.size.height
+        );
+
+        best
+    }
+
+    /// Crop a page whose sprites can't be moved to the smallest size around them
+    fn fit_page(&self, page: PageLayout) -> PageLayout {
+        if !self.options.shrink_to_fit {
+            return page;
+        }
+
+        let padding = self.options.padding;
+        let mut size = Size::new(1, 1);
+        for packed in &page.sprites {
+            size.width = size.width.max(packed.rect.right() + padding);
+            size.height = size.height.max(packed.rect.bottom() + padding);
+        }
+
+        if self.options.power_of_two {
+            size.width = size.width.next_power_of_two();
+            size.height = size.height.next_power_of_two();
+        }
+
+        PageLayout {
+            size: Size::new(
+                size.width.min(page.size.width),
+                size.height.min(page.size.height),
+            ),
+            ..page
+        }
+    }
+
+    /// The space a placed sprite takes up including its padding, clipped to the page
+    fn padded_rect(&self, rect: Rect, page_size: Size) -> Rect {
+        let padding = self.options.padding;
+        let x = rect.x.saturating_sub(padding);
+        let y = rect.y.saturating_sub(padding);
+        let right = (rect.right() + padding).min(page_size.width);
+        let bottom = (rect.bottom() + padding).min(page_size.height);
+
+        Rect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+
+    /// Candidate page side lengths between `min` and `max`, in ascending order.
+    /// Without power of two constraints every height is a candidate (they are
+    /// binary searched), while widths are sampled to bound the number of layouts.
+    fn candidate_sides(&self, min: u32, max: u32, sampled: bool) -> Vec<u32> {
+        const WIDTH_SAMPLES: u32 = 16;
+
+        if min > max {
+            return Vec::new();
+        }
+
+        if self.options.power_of_two {
+            return std::iter::successors(Some(min.next_power_of_two()), |side| {
+                side.checked_mul(2)
+            })
+            .take_while(|&side| side <= max)
+            .collect();
+        }
+
+        if !sampled || max - min < WIDTH_SAMPLES {
+            return (min..=max).collect();
+        }
+
+        let step = (max - min) / WIDTH_SAMPLES;
+        let mut sides: Vec<u32> = (0..WIDTH_SAMPLES).map(|i| min + i * step).collect();
+        sides.push(max);
+        sides
+    }
+
+    fn layout_single_page(
+        &self,
+        sprites: Vec<Sprite>,
+        heuristic: PackerHeuristic,
+        atlas_size: Size,
+    ) -> (PageLayout, Vec<Sprite>) {
+        let mut packer =
+            algorithm::create_packer(heuristic, atlas_size, self.options.allow_rotation);
+        let (packed_sprites, unpacked_sprites) = self.place_sprites(&mut packer, sprites);
+
+        (
+            PageLayout {
+                size: atlas_size,
+                sprites: packed_sprites,
+                occupancy: packer.occupancy(),
+            },
+            unpacked_sprites,
+        )
+    }
+
+    /// Place as many sprites as `packer` has room for, returning the placed and leftover sprites
+    ///
+    /// The frames of a sequence are placed all together or not at all.
+    fn place_sprites(
+        &self,
+        packer: &mut Box<dyn RectPacker>,
+        sprites: Vec<Sprite>,
+    ) -> (Vec<PackedSprite>, Vec<Sprite>) {
         let mut packed_sprites = Vec::new();
         let mut unpacked_sprites = Vec::new();
 
-        for mut sprite in sprites {
-            // Trim sprite to remove transparent borders
-            let original_rect = self.trim_sprite(&mut sprite);
+        for bundle in bundle_sequences(sprites) {
+            // Sequences are tried on a copy that is only kept if every frame fits
+            let mut trial = if bundle.len() > 1 {
+                Some(packer.boxed_clone())
+            } else {
+                None
+            };
+            let target = trial.as_mut().unwrap_or(packer);
 
-            // Account for padding in placement
-            let required_size = Size {
-                width: sprite.size.width + 2 * self.options.padding,
-                height: sprite.size.height + 2 * self.options.padding,
+            let placements: Option<Vec<(Rect, bool)>> = bundle
+                .iter()
+                .map(|sprite| self.place_sprite(target.as_mut(), sprite))
+                .collect();
+
+            let Some(placements) = placements else {
+                unpacked_sprites.extend(bundle);
+                continue;
             };
 
-            if let Some(rect) = packer.pack(required_size) {
-                // Adjust rect to account for padding
-                let sprite_rect = Rect {
-                    x: rect.x + self.options.padding,
-                    y: rect.y + self.options.padding,
-                    width: sprite.size.width,
-                    height: sprite.size.height,
-                };
+            if let Some(trial) = trial {
+                *packer = trial;
+            }
 
+            for (sprite, (rect, rotated)) in bundle.into_iter().zip(placements) {
                 packed_sprites.push(PackedSprite {
+                    trimmed: sprite.sprite_source_size.is_some(),
+                    sprite_source_size: sprite.sprite_source_size,
                     sprite,
-                    rect: sprite_rect,
-                    trimmed: original_rect.is_some(),
-                    sprite_source_size: original_rect,
+                    rect,
+                    rotated,
                 });
-            } else {
-                unpacked_sprites.push(sprite);
             }
         }
 
-        // Create atlas image
-        let image_data = self.render_atlas(&packed_sprites, atlas_size)?;
+        (packed_sprites, unpacked_sprites)
+    }
 
-        Ok((
-            Atlas {
-                page_index,
-                image_data,
-                size: atlas_size,
-                sprites: packed_sprites,
-            },
-            unpacked_sprites,
-        ))
+    /// Place a single sprite, returning its rect without padding and whether it was rotated
+    fn place_sprite(&self, packer: &mut dyn RectPacker, sprite: &Sprite) -> Option<(Rect, bool)> {
+        // Account for padding in placement
+        let required_size = Size {
+            width: sprite.size.width + 2 * self.options.padding,
+            height: sprite.size.height + 2 * self.options.padding,
+        };
+
+        let rect = if self.can_rotate(sprite) {
+            packer.pack(required_size)?
+        } else {
+            packer.pack_upright(required_size)?
+        };
+
+        // The packer swaps the dimensions when it places the sprite rotated
+        let rotated = rect.width != required_size.width;
+        let placed_size = if rotated {
+            sprite.size.rotated()
+        } else {
+            sprite.size
+        };
+
+        // Adjust rect to account for padding
+        let sprite_rect = Rect {
+            x: rect.x + self.options.padding,
+            y: rect.y + self.options.padding,
+            width: placed_size.width,
+            height: placed_size.height,
+        };
+
+        Some((sprite_rect, rotated))
+    }
+
+    /// Whether the packer may rotate this sprite to make it fit
+    fn can_rotate(&self, sprite: &Sprite) -> bool {
+        self.options.allow_rotation && sprite.slice_center.is_none()
     }
 
     fn trim_sprite(&self, sprite: &mut Sprite) -> Option<Rect> {
         use std::io::Cursor;
 
+        if sprite.slice_center.is_some() {
+            return None;
+        }
+
         let img = image::load_from_memory(&sprite.data).ok()?;
         let rgba = img.to_rgba8();
         let width = rgba.width() as usize;
@@ -365,6 +1318,8 @@ impl Packer {
             packed_sprites.len()
         );
 
+        let mut sprite_images = Vec::with_capacity(packed_sprites.len());
+
         for (i, packed_sprite) in packed_sprites.iter().enumerate() {
             log::debug!(
                 "Rendering sprite {} '{}' at ({}, {}) size {}x{}",
@@ -377,7 +1332,11 @@ impl Packer {
             );
 
             let sprite_image = image::load_from_memory(&packed_sprite.sprite.data)?;
-            let sprite_rgba = sprite_image.to_rgba8();
+            let sprite_rgba = if packed_sprite.rotated {
+                image::imageops::rotate90(&sprite_image.to_rgba8())
+            } else {
+                sprite_image.to_rgba8()
+            };
 
             log::debug!(
                 "Loaded sprite image {}x{}",
@@ -385,25 +1344,26 @@ impl Packer {
                 sprite_rgba.height()
             );
 
-            // Copy sprite to atlas at the correct position
-            for y in 0..packed_sprite.rect.height {
-                for x in 0..packed_sprite.rect.width {
-                    if let Some(sprite_pixel) = sprite_rgba.get_pixel_checked(x, y) {
-                        atlas_image.put_pixel(
-                            packed_sprite.rect.x + x,
-                            packed_sprite.rect.y + y,
-                            *sprite_pixel,
-                        );
-                    }
-                }
-            }
+            Self::draw_sprite(&mut atlas_image, packed_sprite, &sprite_rgba);
+            sprite_images.push(sprite_rgba);
 
-            // Apply extrude if configured
-            if self.options.extrude > 0 {
+            log::debug!("Finished rendering sprite '{}'", packed_sprite.sprite.name);
+        }
+
+        // Apply extrude if configured
+        if self.options.extrude > 0 {
+            for packed_sprite in packed_sprites {
                 self.apply_extrude(&mut atlas_image, packed_sprite)?;
             }
 
-            log::debug!("Finished rendering sprite '{}'", packed_sprite.sprite.name);
+            // Extrusion wider than the padding spills onto neighbouring
+            // sprites, so draw them again to keep their edges (and slice
+            // borders) intact
+            if self.options.extrude > self.options.padding {
+                for (packed_sprite, sprite_rgba) in packed_sprites.iter().zip(&sprite_images) {
+                    Self::draw_sprite(&mut atlas_image, packed_sprite, sprite_rgba);
+                }
+            }
         }
 
         log::debug!("Applying alpha bleeding to atlas image");
@@ -416,6 +1376,21 @@ impl Packer {
         Ok(buffer.into_inner())
     }
 
+    /// Copy sprite to atlas at the correct position
+    fn draw_sprite(atlas_image: &mut RgbaImage, packed_sprite: &PackedSprite, sprite: &RgbaImage) {
+        for y in 0..packed_sprite.rect.height {
+            for x in 0..packed_sprite.rect.width {
+                if let Some(sprite_pixel) = sprite.get_pixel_checked(x, y) {
+                    atlas_image.put_pixel(
+                        packed_sprite.rect.x + x,
+                        packed_sprite.rect.y + y,
+                        *sprite_pixel,
+                    );
+                }
+            }
+        }
+    }
+
     fn apply_extrude(
         &self,
         atlas_image: &mut RgbaImage,
@@ -463,10 +1438,18 @@ impl Packer {
         Ok(())
     }
 
-    fn create_manifest(&self, atlases: &[Atlas], input_name: &str) -> Result<AtlasManifest> {
-        let mut manifest = AtlasManifest::new(input_name.to_string());
+    fn create_manifest(&self, atlases: &[Atlas], sheet_name: &str) -> Result<AtlasManifest> {
+        let mut manifest = AtlasManifest::new(sheet_name.to_string());
 
         for atlas in atlases {
+            manifest.add_page(PageInfo {
+                index: atlas.page_index,
+                group: atlas.group.clone(),
+                filename: page_filename(sheet_name, atlas.page_index),
+                size: atlas.size,
+                sprite_count: atlas.sprites.len(),
+            });
+
             for packed_sprite in &atlas.sprites {
                 let sprite_info = SpriteInfo {
                     name: packed_sprite.sprite.name.clone(),
@@ -474,7 +1457,10 @@ impl Packer {
                     source_size: packed_sprite.sprite.size,
                     trimmed: packed_sprite.trimmed,
                     sprite_source_size: packed_sprite.sprite_source_size,
+                    rotated: packed_sprite.rotated,
                     page_index: atlas.page_index,
+                    group: atlas.group.clone(),
+                    slice_center: packed_sprite.sprite.slice_center,
                 };
                 manifest.add_sprite(sprite_info);
             }
@@ -483,3 +1469,520 @@ impl Packer {
         Ok(manifest)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::PackGroup, glob::Glob};
+    use image::{ImageBuffer, Rgba};
+    use relative_path::RelativePathBuf;
+    use std::io::Cursor;
+
+    fn make_asset(path: &str, width: u32, height: u32) -> Asset {
+        let image: RgbaImage = ImageBuffer::from_pixel(width, height, Rgba([255, 0, 0, 255]));
+        let mut buffer = Cursor::new(Vec::new());
+        image
+            .write_to(&mut buffer, image::ImageFormat::Png)
+            .unwrap();
+
+        Asset::new(RelativePathBuf::from(path), buffer.into_inner()).unwrap()
+    }
+
+    fn make_options() -> PackOptions {
+        PackOptions {
+            enabled: true,
+            max_size: (512, 512),
+            padding: 0,
+            extrude: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_shrink_to_fit_small_page() {
+        let assets = vec![
+            make_asset("a.png", 20, 20),
+            make_asset("b.png", 20, 20),
+            make_asset("c.png", 10, 10),
+        ];
+
+        let result = Packer::new(make_options())
+            .pack_assets(&assets, "icons")
+            .unwrap();
+
+        assert_eq!(result.atlases.len(), 1);
+        let size = result.atlases[0].size;
+        assert_eq!(size.area(), 64 * 32);
+        assert!(size.width.is_power_of_two() && size.height.is_power_of_two());
+
+        let page = &result.manifest.pages[0];
+        assert_eq!(page.size, size);
+        assert_eq!(page.sprite_count, 3);
+        assert_eq!(page.filename, "icons-sheet-0.png");
+
+        let bounds = Rect::from_size(result.atlases[0].size);
+        for sprite in result.manifest.sprites.values() {
+            assert!(bounds.contains_rect(&sprite.rect));
+        }
+    }
+
+    #[test]
+    fn test_shrink_to_fit_without_power_of_two() {
+        let assets = vec![make_asset("a.png", 30, 20), make_asset("b.png", 30, 20)];
+
+        let options = PackOptions {
+            power_of_two: false,
+            ..make_options()
+        };
+        let result = Packer::new(options).pack_assets(&assets, "icons").unwrap();
+
+        assert_eq!(result.atlases[0].size.area(), 1200);
+    }
+
+    #[test]
+    fn test_shrink_to_fit_disabled() {
+        let assets = vec![make_asset("a.png", 20, 20)];
+
+        let options = PackOptions {
+            shrink_to_fit: false,
+            ..make_options()
+        };
+        let result = Packer::new(options).pack_assets(&assets, "icons").unwrap();
+
+        assert_eq!(result.atlases[0].size, Size::new(512, 512));
+    }
+
+    #[test]
+    fn test_guillotine_heuristics() {
+        let options = PackOptions {
+            algorithm: PackAlgorithm::Guillotine,
+            free_rect_choice: PackFreeRectChoice::BestAreaFit,
+            split_rule: PackSplitRule::MaximizeArea,
+            ..make_options()
+        };
+        assert_eq!(
+            Packer::new(options.clone()).heuristics_to_try(),
+            [PackerHeuristic::Guillotine(
+                FreeRectChoice::BestAreaFit,
+                SplitRule::MaximizeArea
+            )]
+        );
+
+        // Auto tries every combination of the rules set to it
+        let options = PackOptions {
+            free_rect_choice: PackFreeRectChoice::Auto,
+            split_rule: PackSplitRule::Auto,
+            ..options
+        };
+        let packer = Packer::new(options);
+        assert_eq!(
+            packer.heuristics_to_try().len(),
+            FreeRectChoice::ALL.len() * SplitRule::ALL.len()
+        );
+
+        let assets = vec![make_asset("a.png", 64, 32), make_asset("b.png", 32, 64)];
+        let result = packer.pack_assets(&assets, "ui").unwrap();
+        assert_eq!(result.manifest.sprites.len(), 2);
+    }
+
+    #[test]
+    fn test_sprites_keyed_by_relative_path() {
+        let assets = vec![
+            make_asset("icons/close.png", 16, 16),
+            make_asset("buttons/close.png", 24, 24),
+        ];
+
+        let result = Packer::new(make_options())
+            .pack_assets(&assets, "ui")
+            .unwrap();
+
+        let sprites = &result.manifest.sprites;
+        assert_eq!(sprites.len(), 2);
+        assert_eq!(sprites["icons/close.png"].source_size, Size::new(16, 16));
+        assert_eq!(sprites["buttons/close.png"].source_size, Size::new(24, 24));
+    }
+
+    #[test]
+    fn test_oversized_sprite_errors_by_default() {
+        let assets = vec![make_asset("big.png", 600, 16)];
+
+        let result = Packer::new(make_options()).pack_assets(&assets, "ui");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_overflow_upload_individually() {
+        let assets = vec![
+            make_asset("big.png", 600, 16),
+            make_asset("a.png", 300, 300),
+            make_asset("b.png", 300, 300),
+            make_asset("small.png", 16, 16),
+        ];
+
+        let options = PackOptions {
+            overflow: PackOverflow::UploadIndividually,
+            page_limit: Some(1),
+            ..make_options()
+        };
+        let result = Packer::new(options).pack_assets(&assets, "ui").unwrap();
+
+        assert_eq!(result.atlases.len(), 1);
+        assert!(result.overflow.contains(&"big.png".to_string()));
+        assert_eq!(result.overflow.len(), 2);
+
+        for name in &result.overflow {
+            assert!(!result.manifest.sprites.contains_key(name));
+        }
+        assert_eq!(result.manifest.sprites.len(), 2);
+    }
+
+    #[test]
+    fn test_incremental_keeps_existing_rects() {
+        let packer = Packer::new(make_options());
+        let kept = vec![make_asset("a.png", 64, 64), make_asset("b.png", 32, 32)];
+        let previous = packer.pack_assets(&kept, "ui").unwrap().manifest;
+
+        let new = vec![make_asset("c.png", 32, 32)];
+        let result = packer
+            .pack_incremental(&new, &kept, &previous, "ui")
+            .unwrap();
+
+        assert_eq!(result.atlases.len(), 1);
+        assert_eq!(result.atlases[0].page_index, 0);
+
+        let sprites = &result.manifest.sprites;
+        assert_eq!(sprites["a.png"].rect, previous.sprites["a.png"].rect);
+        assert_eq!(sprites["b.png"].rect, previous.sprites["b.png"].rect);
+        assert!(!sprites["c.png"].rect.intersects(&sprites["a.png"].rect));
+        assert!(!sprites["c.png"].rect.intersects(&sprites["b.png"].rect));
+    }
+
+    #[test]
+    fn test_incremental_uses_configured_algorithm() {
+        let options = PackOptions {
+            algorithm: PackAlgorithm::Guillotine,
+            free_rect_choice: PackFreeRectChoice::Auto,
+            split_rule: PackSplitRule::Auto,
+            ..make_options()
+        };
+        let packer = Packer::new(options);
+        let kept = vec![make_asset("a.png", 64, 64), make_asset("b.png", 32, 32)];
+        let previous = packer.pack_assets(&kept, "ui").unwrap().manifest;
+
+        let new = vec![make_asset("c.png", 48, 16), make_asset("d.png", 16, 48)];
+        let result = packer
+            .pack_incremental(&new, &kept, &previous, "ui")
+            .unwrap();
+
+        assert_eq!(result.atlases.len(), 1);
+        assert_eq!(result.atlases[0].page_index, 0);
+
+        let sprites = &result.manifest.sprites;
+        let rects: Vec<Rect> = ["a.png", "b.png", "c.png", "d.png"]
+            .iter()
+            .map(|name| sprites[*name].rect)
+            .collect();
+        assert_eq!(rects[0], previous.sprites["a.png"].rect);
+        assert_eq!(rects[1], previous.sprites["b.png"].rect);
+
+        for (i, rect) in rects.iter().enumerate() {
+            assert!(!rects[i + 1..].iter().any(|other| other.intersects(rect)));
+        }
+    }
+
+    #[test]
+    fn test_incremental_only_renders_changed_pages() {
+        let options = PackOptions {
+            max_size: (64, 64),
+            ..make_options()
+        };
+        let packer = Packer::new(options);
+
+        // The full sprite fills the first page, leaving room only on the second
+        let kept = vec![make_asset("full.png", 64, 64), make_asset("b.png", 32, 32)];
+        let previous = packer.pack_assets(&kept, "ui").unwrap().manifest;
+        assert_eq!(previous.pages.len(), 2);
+
+        let new = vec![make_asset("c.png", 32, 32)];
+        let result = packer
+            .pack_incremental(&new, &kept, &previous, "ui")
+            .unwrap();
+
+        assert_eq!(result.atlases.len(), 1);
+        assert_eq!(result.atlases[0].page_index, 1);
+        assert!(!result.manifest.sprites.contains_key("full.png"));
+        assert_eq!(result.manifest.sprites["c.png"].page_index, 1);
+    }
+
+    #[test]
+    fn test_incremental_adds_pages_after_previous_ones() {
+        let options = PackOptions {
+            max_size: (64, 64),
+            ..make_options()
+        };
+        let packer = Packer::new(options);
+
+        let kept = vec![make_asset("full.png", 64, 64)];
+        let previous = packer.pack_assets(&kept, "ui").unwrap().manifest;
+
+        let new = vec![make_asset("c.png", 32, 32)];
+        let result = packer
+            .pack_incremental(&new, &kept, &previous, "ui")
+            .unwrap();
+
+        assert_eq!(result.atlases.len(), 1);
+        assert_eq!(result.atlases[0].page_index, 1);
+        assert_eq!(result.manifest.pages[0].filename, "ui-sheet-1.png");
+    }
+
+    #[test]
+    fn test_pages_are_numbered_after_previous_ones() {
+        let packer = Packer::new(make_options());
+        let previous = packer
+            .pack_assets(&[make_asset("a.png", 32, 32)], "ui")
+            .unwrap()
+            .manifest;
+
+        let result = packer
+            .pack_assets_after(&[make_asset("b.png", 32, 32)], &previous, "ui")
+            .unwrap();
+
+        assert_eq!(result.atlases[0].page_index, 1);
+        assert_eq!(result.manifest.pages[0].filename, "ui-sheet-1.png");
+        assert_eq!(result.manifest.sprites["b.png"].page_index, 1);
+    }
+
+    #[test]
+    fn test_options_hash_only_covers_layout_options() {
+        let options = make_options();
+        let hash = Packer::new(options.clone()).options_hash();
+
+        let written = PackOptions {
+            write_atlases: true,
+            atlas_dir: Some("atlases".into()),
+            ..options.clone()
+        };
+        assert_eq!(Packer::new(written).options_hash(), hash);
+
+        let padded = PackOptions {
+            padding: options.padding + 1,
+            ..options
+        };
+        assert_ne!(Packer::new(padded).options_hash(), hash);
+    }
+
+    #[test]
+    fn test_groups_get_their_own_pages() {
+        let assets = vec![
+            make_asset("hud/health.png", 32, 32),
+            make_asset("shop/coin.png", 16, 16),
+            make_asset("misc.png", 8, 8),
+        ];
+
+        let mut options = make_options();
+        for (name, glob) in [("hud", "hud/**"), ("shop", "shop/**")] {
+            options.groups.insert(
+                name.to_string(),
+                PackGroup {
+                    glob: Glob::new(glob).unwrap(),
+                    max_size: None,
+                    power_of_two: None,
+                    shrink_to_fit: None,
+                    padding: Some(4),
+                    extrude: None,
+                    algorithm: None,
+                    heuristic: None,
+                    free_rect_choice: None,
+                    split_rule: None,
+                    page_limit: None,
+                    sort: None,
+                    allow_rotation: None,
+                },
+            );
+        }
+
+        let result = Packer::new(options).pack_assets(&assets, "ui").unwrap();
+
+        let filenames: Vec<&str> = result
+            .manifest
+            .pages
+            .iter()
+            .map(|page| page.filename.as_str())
+            .collect();
+        assert_eq!(
+            filenames,
+            [
+                "ui-hud-sheet-0.png",
+                "ui-shop-sheet-0.png",
+                "ui-sheet-0.png"
+            ]
+        );
+
+        // Each group numbers its own pages, as their file names do
+        let sprites = &result.manifest.sprites;
+        assert_eq!(sprites["hud/health.png"].group.as_deref(), Some("hud"));
+        assert_eq!(sprites["hud/health.png"].page_index, 0);
+        assert_eq!(sprites["shop/coin.png"].group.as_deref(), Some("shop"));
+        assert_eq!(sprites["shop/coin.png"].page_index, 0);
+        assert_eq!(sprites["misc.png"].group, None);
+        assert_eq!(sprites["misc.png"].page_index, 0);
+
+        // Group overrides only apply to their own sprites
+        assert_eq!(sprites["hud/health.png"].rect.x, 4);
+        assert_eq!(sprites["misc.png"].rect.x, 0);
+
+        // Pages remember the padding they were packed with
+        let paddings: Vec<u32> = result.atlases.iter().map(|atlas| atlas.padding).collect();
+        assert_eq!(paddings, [4, 4, 0]);
+    }
+
+    #[test]
+    fn test_sequence_frames_share_a_page() {
+        let assets = vec![
+            make_asset("bar.png", 64, 32),
+            make_asset("walk_0.png", 32, 32),
+            make_asset("walk_1.png", 32, 32),
+            make_asset("walk_2.png", 32, 32),
+        ];
+
+        let options = PackOptions {
+            max_size: (64, 64),
+            sequences: true,
+            ..make_options()
+        };
+        let result = Packer::new(options).pack_assets(&assets, "ui").unwrap();
+
+        // Two frames would fit next to the bar, but the sequence moves as a whole
+        let sprites = &result.manifest.sprites;
+        let page = sprites["walk_0.png"].page_index;
+        assert_ne!(page, sprites["bar.png"].page_index);
+        assert_eq!(sprites["walk_1.png"].page_index, page);
+        assert_eq!(sprites["walk_2.png"].page_index, page);
+    }
+
+    #[test]
+    fn test_sequence_too_large_for_a_page() {
+        let assets: Vec<Asset> = (0..5)
+            .map(|i| make_asset(&format!("walk_{i}.png"), 32, 32))
+            .collect();
+
+        let options = PackOptions {
+            max_size: (64, 64),
+            sequences: true,
+            ..make_options()
+        };
+        let result = Packer::new(options).pack_assets(&assets, "ui");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_sequence_too_large_for_a_page_uploaded_individually() {
+        let mut assets: Vec<Asset> = (0..5)
+            .map(|i| make_asset(&format!("walk_{i}.png"), 32, 32))
+            .collect();
+        assets.push(make_asset("bar.png", 64, 32));
+
+        let options = PackOptions {
+            max_size: (64, 64),
+            sequences: true,
+            overflow: PackOverflow::UploadIndividually,
+            ..make_options()
+        };
+        let result = Packer::new(options).pack_assets(&assets, "ui").unwrap();
+
+        let mut overflow = result.overflow.clone();
+        overflow.sort();
+        assert_eq!(
+            overflow,
+            [
+                "walk_0.png",
+                "walk_1.png",
+                "walk_2.png",
+                "walk_3.png",
+                "walk_4.png"
+            ]
+        );
+        assert!(result.manifest.sprites.contains_key("bar.png"));
+        assert_eq!(result.manifest.sprites.len(), 1);
+    }
+
+    #[test]
+    fn test_sliced_sprites_are_not_trimmed_or_rotated() {
+        let mut image: RgbaImage = ImageBuffer::from_pixel(20, 60, Rgba([0, 0, 0, 0]));
+        for y in 10..50 {
+            for x in 5..15 {
+                image.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        let mut buffer = Cursor::new(Vec::new());
+        image
+            .write_to(&mut buffer, image::ImageFormat::Png)
+            .unwrap();
+
+        let mut asset =
+            Asset::new(RelativePathBuf::from("panel.png"), buffer.into_inner()).unwrap();
+        asset.set_slice_center(Rect::new(4, 4, 12, 52));
+
+        let options = PackOptions {
+            max_size: (64, 64),
+            allow_rotation: true,
+            ..make_options()
+        };
+        let result = Packer::new(options.clone())
+            .pack_assets(std::slice::from_ref(&asset), "ui")
+            .unwrap();
+
+        let sprite = &result.manifest.sprites["panel.png"];
+        assert!(!sprite.trimmed && !sprite.rotated);
+        assert_eq!(sprite.rect.size(), Size::new(20, 60));
+        assert_eq!(sprite.slice_center, Some(Rect::new(4, 4, 12, 52)));
+
+        // Only fits on the page rotated, which a sliced sprite can't be
+        let options = PackOptions {
+            max_size: (64, 32),
+            ..options
+        };
+        assert!(Packer::new(options).pack_assets(&[asset], "ui").is_err());
+    }
+
+    #[test]
+    fn test_extrude_does_not_spill_onto_neighbours() {
+        let mut blue = make_asset("b.png", 10, 10);
+        let image: RgbaImage = ImageBuffer::from_pixel(10, 10, Rgba([0, 0, 255, 255]));
+        let mut buffer = Cursor::new(Vec::new());
+        image
+            .write_to(&mut buffer, image::ImageFormat::Png)
+            .unwrap();
+        blue.data = buffer.into_inner().into();
+
+        let assets = vec![make_asset("a.png", 10, 10), blue];
+
+        let options = PackOptions {
+            max_size: (20, 10),
+            power_of_two: false,
+            extrude: 2,
+            sort: PackSort::Name,
+            ..make_options()
+        };
+        let result = Packer::new(options).pack_assets(&assets, "ui").unwrap();
+
+        let atlas = image::load_from_memory(&result.atlases[0].image_data)
+            .unwrap()
+            .to_rgba8();
+
+        for name in ["a.png", "b.png"] {
+            let rect = result.manifest.sprites[name].rect;
+            let expected = if name == "a.png" {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            };
+
+            for y in rect.y..rect.bottom() {
+                for x in rect.x..rect.right() {
+                    assert_eq!(*atlas.get_pixel(x, y), expected);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use super::{Atlas, PackResult, Rect};
+use anyhow::Context;
+use image::imageops;
+use resvg::{
+    tiny_skia::Pixmap,
+    usvg::{Options, Transform, Tree, fontdb::Database},
+};
+use std::{fmt::Write, io::Cursor, sync::Arc};
+
+/// Stats of a single atlas page, as listed in the preview index
+#[derive(Debug)]
+pub struct PageStats {
+    pub filename: String,
+    pub preview_filename: String,
+    pub width: u32,
+    pub height: u32,
+    pub sprite_count: usize,
+    pub occupancy: f64,
+    /// Area covered by sprites, without their padding
+    pub sprite_area: u64,
+    /// Area of the page that no sprite covers
+    pub wasted_area: u64,
+}
+
+/// The pages of one input or shared atlas
+#[derive(Debug)]
+pub struct SheetReport {
+    pub name: String,
+    pub pages: Vec<PageStats>,
+    /// Sprites that didn't make it onto a page
+    pub overflow: Vec<String>,
+}
+
+impl SheetReport {
+    pub fn new(name: &str, result: &PackResult) -> anyhow::Result<Self> {
+        let pages = result
+            .atlases
+            .iter()
+            .map(|atlas| {
+                let page = result
+                    .manifest
+                    .page(atlas.group.as_deref(), atlas.page_index)
+                    .context("Atlas page is missing from the manifest")?;
+
+                Ok(PageStats::new(&page.filename, atlas))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            pages,
+            overflow: result.overflow.clone(),
+        })
+    }
+}
+
+impl PageStats {
+    fn new(filename: &str, atlas: &Atlas) -> Self {
+        let page_area = atlas.size.width as u64 * atlas.size.height as u64;
+        let sprite_area = atlas
+            .sprites
+            .iter()
+            .map(|packed| packed.rect.width as u64 * packed.rect.height as u64)
+            .sum();
+
+        Self {
+            filename: filename.to_string(),
+            preview_filename: preview_filename(filename),
+            width: atlas.size.width,
+            height: atlas.size.height,
+            sprite_count: atlas.sprites.len(),
+            occupancy: atlas.occupancy,
+            sprite_area,
+            wasted_area: page_area.saturating_sub(sprite_area),
+        }
+    }
+}
+
+/// The file name of a page's preview, e.g. `icons-sheet-0.preview.png`
+pub fn preview_filename(filename: &str) -> String {
+    match filename.strip_suffix(".png") {
+        Some(stem) => format!("{stem}.preview.png"),
+        None => format!("{filename}.preview.png"),
+    }
+}
+
+/// Render an atlas page with the padding around each sprite shaded and
+/// its outline and name drawn on top
+pub fn render_page(atlas: &Atlas, fontdb: Arc<Database>) -> anyhow::Result<Vec<u8>> {
+    let mut page = image::load_from_memory(&atlas.image_data)?.to_rgba8();
+
+    let svg = overlay_svg(atlas);
+    let opt = Options {
+        fontdb,
+        ..Default::default()
+    };
+    let tree = Tree::from_str(&svg, &opt)?;
+
+    let mut pixmap =
+        Pixmap::new(atlas.size.width, atlas.size.height).context("Atlas page is empty")?;
+    resvg::render(&tree, Transform::identity(), &mut pixmap.as_mut());
+
+    let overlay = image::load_from_memory(&pixmap.encode_png()?)?.to_rgba8();
+    imageops::overlay(&mut page, &overlay, 0, 0);
+
+    let mut writer = Cursor::new(Vec::new());
+    page.write_to(&mut writer, image::ImageFormat::Png)?;
+    Ok(writer.into_inner())
+}
+
+fn overlay_svg(atlas: &Atlas) -> String {
+    let (width, height) = (atlas.size.width, atlas.size.height);
+    let padding = atlas.padding;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+
+    for packed in &atlas.sprites {
+        let rect = packed.rect;
+
+        if padding > 0 {
+            let padded = Rect::new(
+                rect.x.saturating_sub(padding),
+                rect.y.saturating_sub(padding),
+                rect.width + 2 * padding,
+                rect.height + 2 * padding,
+            );
+
+            // The padded rect with the sprite cut out of it
+            let _ = write!(
+                svg,
+                r#"<path fill-rule="evenodd" fill="rgb(255,0,255)" fill-opacity="0.35" shape-rendering="crispEdges" d="{} {}"/>"#,
+                rect_path(padded),
+                rect_path(rect)
+            );
+        }
+
+        let _ = write!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="rgb(0,255,0)" stroke-width="1" shape-rendering="crispEdges"/>"#,
+            rect.x as f64 + 0.5,
+            rect.y as f64 + 0.5,
+            rect.width.saturating_sub(1),
+            rect.height.saturating_sub(1)
+        );
+
+        let font_size = rect.height.clamp(6, 12);
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{font_size}" fill="white" stroke="black" stroke-width="0.5">{}</text>"#,
+            rect.x + 2,
+            rect.y + font_size,
+            escape(&packed.sprite.name)
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+fn rect_path(rect: Rect) -> String {
+    format!(
+        "M{} {}h{}v{}h-{}Z",
+        rect.x, rect.y, rect.width, rect.height, rect.width
+    )
+}
+
+/// Escape text for use in HTML or SVG
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An HTML page listing every sheet's pages with their stats and previews
+pub fn index_html(sheets: &[SheetReport]) -> String {
+    let mut html = String::from(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Asphalt pack preview</title>
+<style>
+body { font-family: sans-serif; background: #1e1e1e; color: #ddd; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { padding: 0.3em 0.8em; text-align: right; border-bottom: 1px solid #444; }
+th:first-child, td:first-child { text-align: left; }
+a { color: #8cf; }
+img { max-width: 100%; image-rendering: pixelated; background: repeating-conic-gradient(#444 0% 25%, #333 0% 50%) 0 0 / 16px 16px; }
+</style>
+</head>
+<body>
+<h1>Pack preview</h1>
+"#,
+    );
+
+    for sheet in sheets {
+        let _ = writeln!(html, "<h2>{}</h2>", escape(&sheet.name));
+
+        if sheet.pages.is_empty() {
+            html.push_str("<p>No atlas pages were generated.</p>\n");
+        } else {
+            html.push_str(
+                "<table>\n<tr><th>Page</th><th>Size</th><th>Sprites</th><th>Occupancy</th><th>Sprite area</th><th>Wasted area</th></tr>\n",
+            );
+
+            for page in &sheet.pages {
+                let page_area = page.width as u64 * page.height as u64;
+                let _ = writeln!(
+                    html,
+                    r#"<tr><td><a href="{}">{}</a></td><td>{}x{}</td><td>{}</td><td>{:.1}%</td><td>{} px</td><td>{} px ({:.1}%)</td></tr>"#,
+                    escape(&page.preview_filename),
+                    escape(&page.filename),
+                    page.width,
+                    page.height,
+                    page.sprite_count,
+                    page.occupancy * 100.0,
+                    page.sprite_area,
+                    page.wasted_area,
+                    percentage(page.wasted_area, page_area)
+                );
+            }
+
+            html.push_str("</table>\n");
+        }
+
+        if !sheet.overflow.is_empty() {
+            let _ = writeln!(
+                html,
+                "<p>{} sprite(s) would be uploaded individually: {}</p>",
+                sheet.overflow.len(),
+                escape(&sheet.overflow.join(", "))
+            );
+        }
+
+        for page in &sheet.pages {
+            let _ = writeln!(
+                html,
+                r#"<p><img src="{}" alt="{}"></p>"#,
+                escape(&page.preview_filename),
+                escape(&page.filename)
+            );
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn percentage(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+
+    part as f64 / whole as f64 * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asset::Asset, config::PackOptions, pack::Packer};
+    use image::{Rgba, RgbaImage};
+    use relative_path::RelativePathBuf;
+
+    fn make_asset(path: &str, width: u32, height: u32) -> Asset {
+        let image = RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255]));
+        let mut buffer = Cursor::new(Vec::new());
+        image
+            .write_to(&mut buffer, image::ImageFormat::Png)
+            .unwrap();
+
+        Asset::new(RelativePathBuf::from(path), buffer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_report_and_preview() {
+        let packer = Packer::new(PackOptions {
+            enabled: true,
+            max_size: (64, 64),
+            padding: 1,
+            ..Default::default()
+        });
+        let assets = vec![make_asset("a.png", 16, 16), make_asset("b.png", 8, 8)];
+        let result = packer.pack_assets(&assets, "icons").unwrap();
+
+        let report = SheetReport::new("icons", &result).unwrap();
+        let page = &report.pages[0];
+        assert_eq!(page.preview_filename, "icons-sheet-0.preview.png");
+        assert_eq!(page.sprite_count, 2);
+        assert_eq!(page.sprite_area, 16 * 16 + 8 * 8);
+        assert_eq!(
+            page.wasted_area,
+            (page.width * page.height) as u64 - page.sprite_area
+        );
+
+        let html = index_html(&[report]);
+        assert!(html.contains("icons-sheet-0.preview.png"));
+
+        let atlas = &result.atlases[0];
+        let preview = render_page(atlas, Arc::new(Database::new())).unwrap();
+        let preview = image::load_from_memory(&preview).unwrap().to_rgba8();
+        assert_eq!(preview.dimensions(), (atlas.size.width, atlas.size.height));
+
+        // The outline is drawn over the sprite's edge
+        let rect = atlas.sprites[0].rect;
+        assert_eq!(
+            preview.get_pixel(rect.x, rect.y + rect.height / 2).0,
+            [0, 255, 0, 255]
+        );
+    }
+}
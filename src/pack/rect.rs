@@ -30,6 +30,14 @@ impl Size {
     pub fn fits_in(&self, other: Size) -> bool {
         self.width <= other.width && self.height <= other.height
     }
+
+    /// The size with width and height swapped, as if rotated by 90 degrees
+    pub fn rotated(&self) -> Size {
+        Size {
+            width: self.height,
+            height: self.width,
+        }
+    }
 }
 
 /// A 2D rectangle with position and size
@@ -211,6 +219,13 @@ mod tests {
         assert_eq!(size.min_side(), 100);
     }
 
+    #[test]
+    fn test_size_rotated() {
+        let size = Size::new(30, 120);
+        assert_eq!(size.rotated(), Size::new(120, 30));
+        assert_eq!(size.rotated().rotated(), size);
+    }
+
     #[test]
     fn test_size_fits_in() {
         let small = Size::new(50, 75);
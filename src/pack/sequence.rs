@@ -0,0 +1,90 @@
+//! Detection of numbered frames that make up flipbook sequences
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use relative_path::RelativePath;
+
+/// A sprite's place in a sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The sequence's path relative to the input, e.g. `hero/walk.png` for `hero/walk_3.png`
+    pub sequence: String,
+    pub index: u32,
+}
+
+/// Matches file stems against the configured sequence pattern
+#[derive(Debug, Clone)]
+pub struct SequencePattern {
+    regex: Regex,
+}
+
+impl SequencePattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex =
+            Regex::new(pattern).with_context(|| format!("Invalid sequence pattern '{pattern}'"))?;
+
+        for group in ["name", "frame"] {
+            if !regex.capture_names().flatten().any(|name| name == group) {
+                bail!("Sequence pattern '{pattern}' is missing the '{group}' capture group");
+            }
+        }
+
+        Ok(Self { regex })
+    }
+
+    /// The sequence a sprite belongs to, if its file stem looks like a numbered frame
+    pub fn frame_of(&self, path: &str) -> Option<Frame> {
+        let path = RelativePath::new(path);
+        let stem = path.file_stem()?;
+        let captures = self.regex.captures(stem)?;
+
+        let name = captures.name("name")?.as_str();
+        let index = captures.name("frame")?.as_str().parse().ok()?;
+
+        let file_name = match path.extension() {
+            Some(ext) => format!("{name}.{ext}"),
+            None => name.to_string(),
+        };
+
+        let sequence = match path.parent() {
+            Some(parent) if !parent.as_str().is_empty() => format!("{parent}/{file_name}"),
+            _ => file_name,
+        };
+
+        Some(Frame { sequence, index })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT_PATTERN: &str = r"^(?P<name>.+)[_-](?P<frame>\d+)$";
+
+    #[test]
+    fn test_frame_of_numbered_sprites() {
+        let pattern = SequencePattern::new(DEFAULT_PATTERN).unwrap();
+
+        assert_eq!(
+            pattern.frame_of("hero/walk_12.png"),
+            Some(Frame {
+                sequence: "hero/walk.png".to_string(),
+                index: 12,
+            })
+        );
+        assert_eq!(
+            pattern.frame_of("run-left-3.png"),
+            Some(Frame {
+                sequence: "run-left.png".to_string(),
+                index: 3,
+            })
+        );
+        assert_eq!(pattern.frame_of("hero/idle.png"), None);
+    }
+
+    #[test]
+    fn test_pattern_requires_groups() {
+        assert!(SequencePattern::new(r"^(.+)_(\d+)$").is_err());
+        assert!(SequencePattern::new(r"^(?P<name>.+)_(?P<frame>\d+$").is_err());
+    }
+}
@@ -1,5 +1,11 @@
 use super::{BackendSyncResult, SyncBackend};
-use crate::{asset::Asset, sync::SyncState};
+use crate::{
+    asset::Asset,
+    config::UpdateStrategy,
+    sync::{SyncResult, SyncState},
+};
+use anyhow::bail;
+use log::{info, warn};
 use std::sync::Arc;
 use tokio::time;
 
@@ -16,7 +22,7 @@ impl SyncBackend for CloudBackend {
     async fn sync(
         &self,
         state: Arc<SyncState>,
-        _input_name: String,
+        input_name: String,
         asset: &Asset,
     ) -> anyhow::Result<Option<BackendSyncResult>> {
         if cfg!(feature = "mock_cloud") {
@@ -24,8 +30,78 @@ impl SyncBackend for CloudBackend {
             return Ok(Some(BackendSyncResult::Cloud(1337)));
         }
 
-        let asset_id = state.client.upload(asset).await?;
+        // An upload a previous sync gave up waiting on is picked up again,
+        // unless its operation failed
+        if let Some(pending) = state
+            .existing_lockfile
+            .get_pending(&input_name, &asset.hash)
+        {
+            let operation_id = pending.operation_id.clone();
+            info!("Resuming the upload of {} from a previous sync", asset.path);
 
-        Ok(Some(BackendSyncResult::Cloud(asset_id)))
+            match wait(&state, &input_name, asset, operation_id).await {
+                Ok(result) => return Ok(Some(result)),
+                Err(err) => warn!("Uploading {} again: {err:#}", asset.path),
+            }
+        }
+
+        let operation_id = match previous_version(&state, &input_name, asset) {
+            Some(asset_id) => match state.client.update(asset_id, asset).await {
+                Ok(operation_id) => operation_id,
+                Err(err) if state.update_fallbacks.get(&input_name) != Some(&false) => {
+                    warn!(
+                        "Could not upload {} as a new version of asset {asset_id}, uploading it as a new asset instead: {err:#}",
+                        asset.path
+                    );
+                    state.client.create_asset(asset).await?
+                }
+                Err(err) => return Err(err),
+            },
+            None => state.client.create_asset(asset).await?,
+        };
+
+        let result = wait(&state, &input_name, asset, operation_id).await?;
+
+        Ok(Some(result))
+    }
+}
+
+/// The asset a changed file was uploaded as before, if its input uploads
+/// changes as new versions
+fn previous_version(state: &SyncState, input_name: &str, asset: &Asset) -> Option<u64> {
+    if state.update_strategies.get(input_name) != Some(&UpdateStrategy::NewVersion) {
+        return None;
+    }
+
+    state
+        .existing_lockfile
+        .find_by_path(input_name, &asset.path)
+        .map(|entry| entry.asset_id)
+}
+
+/// Wait for an upload's operation, which is recorded in the lockfile first
+/// so a later sync can resume it if this one gives up
+async fn wait(
+    state: &SyncState,
+    input_name: &str,
+    asset: &Asset,
+    operation_id: String,
+) -> anyhow::Result<BackendSyncResult> {
+    state
+        .result_tx
+        .send(SyncResult {
+            input_name: input_name.to_string(),
+            hash: asset.hash.clone(),
+            path: asset.path.clone(),
+            slice_center: asset.slice_center,
+            backend: BackendSyncResult::Pending(operation_id.clone()),
+        })
+        .await?;
+
+    match state.client.wait_for_operation(&operation_id).await? {
+        Some(asset_id) => Ok(BackendSyncResult::Cloud(asset_id)),
+        None => bail!(
+            "Roblox is still processing the upload (operation {operation_id}), the next sync will check on it again"
+        ),
     }
 }
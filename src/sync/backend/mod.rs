@@ -10,6 +10,8 @@ pub mod studio;
 pub enum BackendSyncResult {
     Cloud(u64),
     Studio(String),
+    /// An upload Roblox is still processing, by its operation ID
+    Pending(String),
 }
 
 pub trait SyncBackend {
@@ -1,6 +1,10 @@
+use super::walk::variant_of;
 use crate::{
     config,
-    pack::rect::{Rect, Size},
+    pack::{
+        rect::{Rect, Size},
+        sequence::SequencePattern,
+    },
 };
 use anyhow::bail;
 use relative_path::{RelativePath, RelativePathBuf};
@@ -14,6 +18,10 @@ pub enum Node {
     #[allow(dead_code)]
     Number(u64),
     AtlasSprite(AtlasSpriteData),
+    Flipbook(FlipbookData),
+    SlicedImage(SlicedImageData),
+    /// Resolution variants of one image, ordered by scale
+    Variants(Vec<VariantData>),
 }
 
 #[derive(Clone)]
@@ -23,8 +31,49 @@ pub struct AtlasSpriteData {
     pub size: Size,
     pub trimmed: bool,
     pub sprite_source_size: Option<Rect>,
+    /// The sprite is stored rotated 90 degrees clockwise in the atlas
+    pub rotated: bool,
+    /// The nine-slice center, relative to the sprite
+    pub slice_center: Option<Rect>,
 }
 
+impl AtlasSpriteData {
+    pub fn from_sprite_info(image: &str, info: &crate::lockfile::SpriteInfo) -> Self {
+        Self {
+            image: image.to_string(),
+            rect: info.rect,
+            size: info.source_size,
+            trimmed: info.trimmed,
+            sprite_source_size: info.sprite_source_size,
+            rotated: info.rotated,
+            slice_center: info.slice_center,
+        }
+    }
+}
+
+/// An image meant to be scaled with `ScaleType.Slice`
+#[derive(Clone)]
+pub struct SlicedImageData {
+    pub image: String,
+    pub slice_center: Rect,
+}
+
+/// An animation made of numbered atlas sprites
+#[derive(Clone)]
+pub struct FlipbookData {
+    /// Frames ordered by their frame number
+    pub frames: Vec<AtlasSpriteData>,
+    pub fps: Option<f64>,
+}
+
+/// An image at one of the resolutions it comes in
+#[derive(Clone)]
+pub struct VariantData {
+    pub scale: f64,
+    pub node: Node,
+}
+
+#[derive(Clone, Copy)]
 pub enum Language {
     TypeScript,
     Luau,
@@ -34,9 +83,10 @@ pub fn create_node(source: &BTreeMap<RelativePathBuf, Node>, config: &config::Co
     let mut root = Node::Table(BTreeMap::new());
 
     for (path, node) in source {
-        let value = match node {
-            Node::String(s) if config.content => Node::Content(s.clone()),
-            other => other.clone(),
+        let value = if config.content {
+            to_content(node)
+        } else {
+            node.clone()
         };
 
         match config.style {
@@ -62,6 +112,117 @@ pub fn create_node(source: &BTreeMap<RelativePathBuf, Node>, config: &config::Co
     root
 }
 
+/// The node with its plain asset ids turned into `Content`
+fn to_content(node: &Node) -> Node {
+    match node {
+        Node::String(s) => Node::Content(s.clone()),
+        Node::Variants(variants) => Node::Variants(
+            variants
+                .iter()
+                .map(|variant| VariantData {
+                    scale: variant.scale,
+                    node: to_content(&variant.node),
+                })
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Take the atlas sprites that are frames of a sequence out of `source`,
+/// returning the frames of each sequence in order. It takes at least two
+/// frames to make a sequence.
+pub fn take_sequences(
+    source: &mut BTreeMap<RelativePathBuf, Node>,
+    pattern: &SequencePattern,
+) -> BTreeMap<RelativePathBuf, Vec<AtlasSpriteData>> {
+    let mut sequences = BTreeMap::<RelativePathBuf, Vec<(u32, RelativePathBuf)>>::new();
+
+    for (path, node) in source.iter() {
+        if let Node::AtlasSprite(_) = node
+            && let Some(frame) = pattern.frame_of(path.as_str())
+        {
+            sequences
+                .entry(RelativePathBuf::from(frame.sequence))
+                .or_default()
+                .push((frame.index, path.clone()));
+        }
+    }
+
+    sequences.retain(|sequence, frames| {
+        if frames.len() < 2 {
+            return false;
+        }
+
+        if source.contains_key(sequence) {
+            log::warn!(
+                "Sequence '{sequence}' has the same name as another asset, its frames will stay separate"
+            );
+            return false;
+        }
+
+        true
+    });
+
+    sequences
+        .into_iter()
+        .map(|(sequence, mut frames)| {
+            frames.sort_by_key(|(index, _)| *index);
+
+            let frames = frames
+                .into_iter()
+                .filter_map(|(_, path)| match source.remove(&path) {
+                    Some(Node::AtlasSprite(data)) => Some(data),
+                    _ => None,
+                })
+                .collect();
+
+            (sequence, frames)
+        })
+        .collect()
+}
+
+/// Take resolution variants such as `icon@2x.png` out of `source`, grouping
+/// them with the image they're a variant of (which counts as a scale of 1)
+pub fn take_variants(
+    source: &mut BTreeMap<RelativePathBuf, Node>,
+) -> BTreeMap<RelativePathBuf, Vec<VariantData>> {
+    let mut groups = BTreeMap::<RelativePathBuf, Vec<(f64, RelativePathBuf)>>::new();
+
+    for path in source.keys() {
+        if let Some((base, scale)) = variant_of(path) {
+            groups.entry(base).or_default().push((scale, path.clone()));
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(base, mut paths)| {
+            if source.contains_key(&base) {
+                paths.push((1.0, base.clone()));
+            }
+
+            paths.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut variants: Vec<VariantData> = Vec::with_capacity(paths.len());
+            for (scale, path) in paths {
+                let Some(node) = source.remove(&path) else {
+                    continue;
+                };
+
+                if variants.last().is_some_and(|last| last.scale == scale) {
+                    log::warn!("{path} has the same scale as another variant of {base}, it will be left out");
+                    continue;
+                }
+
+                variants.push(VariantData { scale, node });
+            }
+
+            (base, variants)
+        })
+        .collect()
+}
+
 fn normalize_path_components(
     path: &RelativePath,
     strip_extensions: bool,
@@ -169,6 +330,69 @@ pub fn generate_code(
     })
 }
 
+/// A module with a helper that picks the resolution variant to show
+pub fn generate_variant_helper(lang: Language) -> String {
+    let body = match lang {
+        Language::TypeScript => {
+            r#"interface Variant<T> {
+	readonly scale: number;
+	readonly asset: T;
+}
+
+declare const variants: {
+	/** The scale of the viewport compared to a 1080 pixel tall screen */
+	viewportScale(): number;
+	/** The smallest variant at least as large as the scale (the viewport's by default), or the largest one */
+	pick<T>(variants: ReadonlyArray<Variant<T>>, scale?: number): T;
+};
+
+export = variants"#
+        }
+        Language::Luau => {
+            r#"local Workspace = game:GetService("Workspace")
+
+-- The viewport height that variants with a scale of 1 are made for
+local BASE_HEIGHT = 1080
+
+local variants = {}
+
+-- The scale of the viewport compared to a 1080 pixel tall screen
+function variants.viewportScale(): number
+	local camera = Workspace.CurrentCamera
+	if camera == nil then
+		return 1
+	end
+
+	return camera.ViewportSize.Y / BASE_HEIGHT
+end
+
+-- The smallest variant at least as large as the scale (the viewport's by default), or the largest one
+function variants.pick<T>(entries: { { scale: number, asset: T } }, scale: number?): T
+	local target = scale or variants.viewportScale()
+
+	for _, variant in entries do
+		if variant.scale >= target then
+			return variant.asset
+		end
+	end
+
+	return entries[#entries].asset
+end
+
+return variants"#
+        }
+    };
+
+    let comment = match lang {
+        Language::TypeScript => "//",
+        Language::Luau => "--",
+    };
+
+    format!(
+        "{comment} This file is automatically @generated by Asphalt.\n{comment} It is not intended for manual editing.\n{body}"
+    )
+}
+
 fn generate_typescript(name: &str, node: &Node) -> String {
     let body = generate_ts_node(node, 0);
     format!(
@@ -200,15 +424,47 @@ fn generate_ts_node(node: &Node, indent: usize) -> String {
         Node::String(_) => "string".to_string(),
         Node::Content(_) => "Content".to_string(),
         Node::Number(_) => "number".to_string(),
-        Node::AtlasSprite(_) => {
+        Node::AtlasSprite(_) => generate_ts_sprite_type(indent),
+        Node::SlicedImage(_) => {
             let mut result = String::from("{\n");
             let tab = "\t".repeat(indent + 1);
             result.push_str(&format!("{}readonly image: string;\n", tab));
-            result.push_str(&format!("{}readonly imageRectOffset: Vector2;\n", tab));
-            result.push_str(&format!("{}readonly imageRectSize: Vector2;\n", tab));
-            result.push_str(&format!("{}readonly sourceSize?: Vector2;\n", tab));
-            result.push_str(&format!("{}readonly spriteSourceOffset?: Vector2;\n", tab));
-            result.push_str(&format!("{}readonly trimmed: boolean;\n", tab));
+            result.push_str(&format!("{}readonly sliceCenter: Rect;\n", tab));
+            result.push_str(&"\t".repeat(indent));
+            result.push('}');
+            result
+        }
+        Node::Variants(variants) => {
+            // Variants can differ in kind, such as when only some of them are packed
+            let mut asset_types: Vec<String> = Vec::new();
+            for variant in variants {
+                let asset_type = generate_ts_node(&variant.node, indent + 1);
+                if !asset_types.contains(&asset_type) {
+                    asset_types.push(asset_type);
+                }
+            }
+
+            let mut result = String::from("ReadonlyArray<{\n");
+            let tab = "\t".repeat(indent + 1);
+            result.push_str(&format!("{}readonly scale: number;\n", tab));
+            result.push_str(&format!(
+                "{}readonly asset: {};\n",
+                tab,
+                asset_types.join(" | ")
+            ));
+            result.push_str(&"\t".repeat(indent));
+            result.push_str("}>");
+            result
+        }
+        Node::Flipbook(_) => {
+            let mut result = String::from("{\n");
+            let tab = "\t".repeat(indent + 1);
+            result.push_str(&format!(
+                "{}readonly frames: ReadonlyArray<{}>;\n",
+                tab,
+                generate_ts_sprite_type(indent + 1)
+            ));
+            result.push_str(&format!("{}readonly fps?: number;\n", tab));
             result.push_str(&"\t".repeat(indent));
             result.push('}');
             result
@@ -216,6 +472,22 @@ fn generate_ts_node(node: &Node, indent: usize) -> String {
     }
 }
 
+fn generate_ts_sprite_type(indent: usize) -> String {
+    let mut result = String::from("{\n");
+    let tab = "\t".repeat(indent + 1);
+    result.push_str(&format!("{}readonly image: string;\n", tab));
+    result.push_str(&format!("{}readonly imageRectOffset: Vector2;\n", tab));
+    result.push_str(&format!("{}readonly imageRectSize: Vector2;\n", tab));
+    result.push_str(&format!("{}readonly sourceSize?: Vector2;\n", tab));
+    result.push_str(&format!("{}readonly spriteSourceOffset?: Vector2;\n", tab));
+    result.push_str(&format!("{}readonly trimmed: boolean;\n", tab));
+    result.push_str(&format!("{}readonly rotated?: boolean;\n", tab));
+    result.push_str(&format!("{}readonly sliceCenter?: Rect;\n", tab));
+    result.push_str(&"\t".repeat(indent));
+    result.push('}');
+    result
+}
+
 fn generate_luau(name: &str, node: &Node) -> String {
     let body = generate_luau_node(node, 0);
     format!(
@@ -246,34 +518,106 @@ fn generate_luau_node(node: &Node, indent: usize) -> String {
         Node::String(s) => format!("\"{s}\""),
         Node::Content(s) => format!("Content.fromUri(\"{s}\")"),
         Node::Number(n) => format!("{n}"),
-        Node::AtlasSprite(data) => {
+        Node::AtlasSprite(data) => generate_luau_sprite(data, indent),
+        Node::SlicedImage(data) => {
             let tab = "\t".repeat(indent + 1);
             let mut result = String::from("{\n");
             result.push_str(&format!("{}image = \"{}\",\n", tab, data.image));
             result.push_str(&format!(
-                "{}imageRectOffset = Vector2.new({}, {}),\n",
-                tab, data.rect.x, data.rect.y
-            ));
-            result.push_str(&format!(
-                "{}imageRectSize = Vector2.new({}, {}),\n",
-                tab, data.rect.width, data.rect.height
-            ));
-            result.push_str(&format!("{}trimmed = {},\n", tab, data.trimmed));
-            result.push_str(&format!(
-                "{}sourceSize = Vector2.new({}, {}),\n",
-                tab, data.size.width, data.size.height
+                "{}sliceCenter = {},\n",
+                tab,
+                luau_rect(data.slice_center)
             ));
-            if let Some(sprite_source) = data.sprite_source_size {
+            result.push_str(&"\t".repeat(indent));
+            result.push('}');
+            result
+        }
+        Node::Variants(variants) => {
+            let tab = "\t".repeat(indent + 1);
+            let field_tab = "\t".repeat(indent + 2);
+            let mut result = String::from("{\n");
+            for variant in variants {
+                result.push_str(&format!("{}{{\n", tab));
+                result.push_str(&format!("{}scale = {},\n", field_tab, variant.scale));
                 result.push_str(&format!(
-                    "{}spriteSourceOffset = Vector2.new({}, {}),\n",
-                    tab, sprite_source.x, sprite_source.y
+                    "{}asset = {},\n",
+                    field_tab,
+                    generate_luau_node(&variant.node, indent + 2)
                 ));
+                result.push_str(&format!("{}}},\n", tab));
             }
             result.push_str(&"\t".repeat(indent));
             result.push('}');
             result
         }
+        Node::Flipbook(data) => {
+            let tab = "\t".repeat(indent + 1);
+            let frame_tab = "\t".repeat(indent + 2);
+            let mut result = String::from("{\n");
+            result.push_str(&format!("{}frames = {{\n", tab));
+            for frame in &data.frames {
+                result.push_str(&frame_tab);
+                result.push_str(&generate_luau_sprite(frame, indent + 2));
+                result.push_str(",\n");
+            }
+            result.push_str(&format!("{}}},\n", tab));
+            if let Some(fps) = data.fps {
+                result.push_str(&format!("{}fps = {},\n", tab, fps));
+            }
+            result.push_str(&"\t".repeat(indent));
+            result.push('}');
+            result
+        }
+    }
+}
+
+fn generate_luau_sprite(data: &AtlasSpriteData, indent: usize) -> String {
+    let tab = "\t".repeat(indent + 1);
+    let mut result = String::from("{\n");
+    result.push_str(&format!("{}image = \"{}\",\n", tab, data.image));
+    result.push_str(&format!(
+        "{}imageRectOffset = Vector2.new({}, {}),\n",
+        tab, data.rect.x, data.rect.y
+    ));
+    result.push_str(&format!(
+        "{}imageRectSize = Vector2.new({}, {}),\n",
+        tab, data.rect.width, data.rect.height
+    ));
+    result.push_str(&format!("{}trimmed = {},\n", tab, data.trimmed));
+    result.push_str(&format!(
+        "{}sourceSize = Vector2.new({}, {}),\n",
+        tab, data.size.width, data.size.height
+    ));
+    if let Some(sprite_source) = data.sprite_source_size {
+        result.push_str(&format!(
+            "{}spriteSourceOffset = Vector2.new({}, {}),\n",
+            tab, sprite_source.x, sprite_source.y
+        ));
+    }
+    if data.rotated {
+        result.push_str(&format!("{}rotated = true,\n", tab));
     }
+    if let Some(slice_center) = data.slice_center {
+        result.push_str(&format!(
+            "{}sliceCenter = {},\n",
+            tab,
+            luau_rect(slice_center)
+        ));
+    }
+    result.push_str(&"\t".repeat(indent));
+    result.push('}');
+    result
+}
+
+/// Roblox rects are made from their min and max corners
+fn luau_rect(rect: Rect) -> String {
+    format!(
+        "Rect.new({}, {}, {}, {})",
+        rect.x,
+        rect.y,
+        rect.right(),
+        rect.bottom()
+    )
 }
 
 fn is_valid_ident_char_start(value: char) -> bool {
@@ -452,6 +796,8 @@ mod tests {
             } else {
                 None
             },
+            rotated: false,
+            slice_center: None,
         })
     }
 
@@ -589,6 +935,8 @@ mod tests {
             size: Size::new(2048, 2048),
             trimmed: true,
             sprite_source_size: Some(Rect::new(512, 512, 1024, 1024)),
+            rotated: false,
+            slice_center: None,
         });
         let mut map = BTreeMap::new();
         map.insert("edge_case".to_string(), sprite);
@@ -603,6 +951,234 @@ mod tests {
         insta::assert_snapshot!(code);
     }
 
+    #[test]
+    fn test_atlas_sprite_luau_rotated() {
+        let sprite = Node::AtlasSprite(AtlasSpriteData {
+            image: "rbxassetid://123456789".to_string(),
+            rect: Rect::new(0, 0, 128, 32),
+            size: Size::new(32, 128),
+            trimmed: false,
+            sprite_source_size: None,
+            rotated: true,
+            slice_center: None,
+        });
+        let mut map = BTreeMap::new();
+        map.insert("strip".to_string(), sprite);
+        let root = Node::Table(map);
+        let code = generate_code(
+            Language::Luau,
+            "strip",
+            &root,
+            &config::InputNamingConvention::CamelCase,
+        )
+        .unwrap();
+        insta::assert_snapshot!(code);
+    }
+
+    fn make_flipbook_node() -> Node {
+        let frames = (0..2)
+            .map(|i| AtlasSpriteData {
+                image: "rbxassetid://123456789".to_string(),
+                rect: Rect::new(i * 32, 0, 32, 32),
+                size: Size::new(32, 32),
+                trimmed: false,
+                sprite_source_size: None,
+                rotated: false,
+                slice_center: None,
+            })
+            .collect();
+
+        let mut map = BTreeMap::new();
+        map.insert(
+            "walk".to_string(),
+            Node::Flipbook(FlipbookData {
+                frames,
+                fps: Some(12.0),
+            }),
+        );
+        Node::Table(map)
+    }
+
+    #[test]
+    fn test_flipbook_luau() {
+        let code = generate_code(
+            Language::Luau,
+            "hero",
+            &make_flipbook_node(),
+            &config::InputNamingConvention::CamelCase,
+        )
+        .unwrap();
+        insta::assert_snapshot!(code);
+    }
+
+    #[test]
+    fn test_flipbook_typescript() {
+        let code = generate_code(
+            Language::TypeScript,
+            "hero",
+            &make_flipbook_node(),
+            &config::InputNamingConvention::CamelCase,
+        )
+        .unwrap();
+        insta::assert_snapshot!(code);
+    }
+
+    fn make_sliced_nodes() -> Node {
+        let mut map = BTreeMap::new();
+        map.insert(
+            "button".to_string(),
+            Node::SlicedImage(SlicedImageData {
+                image: "rbxassetid://111".to_string(),
+                slice_center: Rect::new(8, 8, 48, 16),
+            }),
+        );
+        map.insert(
+            "panel".to_string(),
+            Node::AtlasSprite(AtlasSpriteData {
+                image: "rbxassetid://222".to_string(),
+                rect: Rect::new(64, 0, 32, 32),
+                size: Size::new(32, 32),
+                trimmed: false,
+                sprite_source_size: None,
+                rotated: false,
+                slice_center: Some(Rect::new(4, 4, 24, 24)),
+            }),
+        );
+        Node::Table(map)
+    }
+
+    #[test]
+    fn test_sliced_luau() {
+        let code = generate_code(
+            Language::Luau,
+            "ui",
+            &make_sliced_nodes(),
+            &config::InputNamingConvention::CamelCase,
+        )
+        .unwrap();
+        insta::assert_snapshot!(code);
+    }
+
+    #[test]
+    fn test_sliced_typescript() {
+        let code = generate_code(
+            Language::TypeScript,
+            "ui",
+            &make_sliced_nodes(),
+            &config::InputNamingConvention::CamelCase,
+        )
+        .unwrap();
+        insta::assert_snapshot!(code);
+    }
+
+    fn make_variants_node() -> Node {
+        let mut map = BTreeMap::new();
+        map.insert(
+            "icon".to_string(),
+            Node::Variants(vec![
+                VariantData {
+                    scale: 1.0,
+                    node: Node::String("rbxassetid://1".to_string()),
+                },
+                VariantData {
+                    scale: 2.0,
+                    node: make_atlas_sprite_node(false),
+                },
+            ]),
+        );
+        Node::Table(map)
+    }
+
+    #[test]
+    fn test_variants_luau() {
+        let code = generate_code(
+            Language::Luau,
+            "ui",
+            &make_variants_node(),
+            &config::InputNamingConvention::CamelCase,
+        )
+        .unwrap();
+        insta::assert_snapshot!(code);
+    }
+
+    #[test]
+    fn test_variants_typescript() {
+        let code = generate_code(
+            Language::TypeScript,
+            "ui",
+            &make_variants_node(),
+            &config::InputNamingConvention::CamelCase,
+        )
+        .unwrap();
+        insta::assert_snapshot!(code);
+    }
+
+    #[test]
+    fn test_variant_helper() {
+        insta::assert_snapshot!(generate_variant_helper(Language::Luau));
+    }
+
+    #[test]
+    fn test_take_variants() {
+        let image = |id: u64| Node::String(format!("rbxassetid://{id}"));
+
+        let mut source = BTreeMap::new();
+        source.insert(RelativePathBuf::from("ui/icon.png"), image(1));
+        source.insert(RelativePathBuf::from("ui/icon@3x.png"), image(3));
+        source.insert(RelativePathBuf::from("ui/icon@2x.png"), image(2));
+        source.insert(RelativePathBuf::from("ui/logo@1.5x.png"), image(4));
+        source.insert(RelativePathBuf::from("ui/me@home.png"), image(5));
+
+        let variants = take_variants(&mut source);
+
+        let scales: Vec<f64> = variants[&RelativePathBuf::from("ui/icon.png")]
+            .iter()
+            .map(|variant| variant.scale)
+            .collect();
+        assert_eq!(scales, [1.0, 2.0, 3.0]);
+        assert_eq!(variants[&RelativePathBuf::from("ui/logo.png")].len(), 1);
+
+        assert_eq!(source.len(), 1);
+        assert!(source.contains_key(&RelativePathBuf::from("ui/me@home.png")));
+    }
+
+    #[test]
+    fn test_take_sequences() {
+        let pattern = SequencePattern::new(r"^(?P<name>.+)[_-](?P<frame>\d+)$").unwrap();
+        let sprite = |x| {
+            Node::AtlasSprite(AtlasSpriteData {
+                image: "rbxassetid://1".to_string(),
+                rect: Rect::new(x, 0, 8, 8),
+                size: Size::new(8, 8),
+                trimmed: false,
+                sprite_source_size: None,
+                rotated: false,
+                slice_center: None,
+            })
+        };
+
+        let mut source = BTreeMap::new();
+        source.insert(RelativePathBuf::from("hero/walk_10.png"), sprite(10));
+        source.insert(RelativePathBuf::from("hero/walk_2.png"), sprite(2));
+        source.insert(RelativePathBuf::from("hero/walk_1.png"), sprite(1));
+        source.insert(RelativePathBuf::from("icon_1.png"), sprite(0));
+        source.insert(
+            RelativePathBuf::from("button_1.png"),
+            Node::String("rbxassetid://2".to_string()),
+        );
+
+        let sequences = take_sequences(&mut source, &pattern);
+
+        assert_eq!(sequences.len(), 1);
+        let frames = &sequences[&RelativePathBuf::from("hero/walk.png")];
+        let offsets: Vec<u32> = frames.iter().map(|frame| frame.rect.x).collect();
+        assert_eq!(offsets, [1, 2, 10]);
+
+        // Lone frames and non-sprites are left alone
+        assert_eq!(source.len(), 2);
+        assert!(source.contains_key(&RelativePathBuf::from("icon_1.png")));
+    }
+
     #[test]
     fn test_naming_conventions() {
         assert_eq!(
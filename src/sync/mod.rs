@@ -1,33 +1,39 @@
 use crate::{
     asset::Asset,
     auth::Auth,
-    cli::{SyncArgs, SyncTarget},
-    config::{Config, Input, PackOptions},
-    lockfile::{Lockfile, LockfileEntry, RawLockfile},
-    pack::{self, Packer},
+    cli::{RepackArgs, SyncArgs, SyncTarget},
+    config::{Config, Input, PackOptions, UpdateStrategy},
+    lockfile::{Lockfile, LockfileEntry, PendingOperation, RawLockfile},
+    pack::{self, Packer, rect::Rect, sequence::SequencePattern},
     web_api::WebApiClient,
 };
 use anyhow::{Context, Result, bail};
 use backend::BackendSyncResult;
 use indicatif::MultiProgress;
-use log::{info, warn};
+use log::{debug, info, warn};
 use owo_colors::OwoColorize;
-use relative_path::RelativePathBuf;
+use relative_path::{RelativePath, RelativePathBuf};
 use resvg::usvg::fontdb;
+use serde::Deserialize;
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
     sync::Arc,
 };
 use tokio::{
     fs,
     sync::mpsc::{self, Receiver, Sender},
 };
-use walk::{DuplicateFile, WalkedFile};
+use walk::{AnimationFile, DuplicateFile, WalkedFile};
+
+pub use preview::pack_preview;
 
 mod backend;
 mod codegen;
 mod perform;
+mod preview;
 mod process;
+mod sheet;
 mod walk;
 
 pub struct SyncState {
@@ -41,9 +47,70 @@ pub struct SyncState {
     font_db: Arc<fontdb::Database>,
 
     client: WebApiClient,
+    /// How many assets are synced at once
+    concurrency: usize,
+    /// How each input uploads changed files
+    update_strategies: HashMap<String, UpdateStrategy>,
+    /// Whether each input uploads a new asset when a new version is rejected
+    update_fallbacks: HashMap<String, bool>,
+}
+
+/// Which inputs get their sprites packed from scratch instead of reusing lockfile entries
+struct RepackScope {
+    input: Option<String>,
+}
+
+impl RepackScope {
+    fn includes(&self, input_name: &str) -> bool {
+        self.input.as_deref().is_none_or(|name| name == input_name)
+    }
+
+    /// The packed inputs to repack. Inputs sharing an atlas are repacked
+    /// together, so naming one of them brings in every other one.
+    fn inputs(
+        &self,
+        inputs: &HashMap<String, Input>,
+        is_packed: impl Fn(&Input) -> bool,
+    ) -> Vec<String> {
+        let packed_name = |(input_name, input): (&String, &Input)| {
+            input.atlas.clone().unwrap_or_else(|| input_name.clone())
+        };
+
+        let atlases: Vec<String> = inputs
+            .iter()
+            .filter(|(input_name, input)| self.includes(input_name) && is_packed(input))
+            .map(packed_name)
+            .collect();
+
+        let mut names: Vec<String> = inputs
+            .iter()
+            .filter(|(_, input)| is_packed(input))
+            .filter(|&entry| atlases.contains(&packed_name(entry)))
+            .map(|(input_name, _)| input_name.clone())
+            .collect();
+        names.sort();
+        names
+    }
 }
 
 pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
+    run(multi_progress, args, None).await
+}
+
+pub async fn repack(multi_progress: MultiProgress, args: RepackArgs) -> Result<()> {
+    if !matches!(args.sync.target, SyncTarget::Cloud) {
+        bail!("Repacking only applies to the cloud target");
+    }
+
+    let scope = RepackScope { input: args.input };
+    run(multi_progress, args.sync, Some(scope)).await
+}
+
+async fn run(
+    multi_progress: MultiProgress,
+    args: SyncArgs,
+    repack: Option<RepackScope>,
+) -> Result<()> {
     if args.dry_run && !matches!(args.target, SyncTarget::Cloud) {
         bail!("A dry run doesn't make sense in this context");
     }
@@ -51,11 +118,27 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
     let config = Config::read().await?;
     let codegen_config = config.codegen.clone();
 
+    if let Some(name) = repack.as_ref().and_then(|scope| scope.input.as_ref()) {
+        let input = config
+            .inputs
+            .get(name)
+            .with_context(|| format!("No input named '{name}' found in configuration"))?;
+
+        if !should_pack(input, &args) {
+            bail!("Input '{name}' does not have packing enabled, so there is nothing to repack");
+        }
+    }
+
     let lockfile = RawLockfile::read().await?.into_lockfile()?;
 
     let key_required = matches!(args.target, SyncTarget::Cloud) && !args.dry_run;
     let auth = Auth::new(args.api_key.clone(), key_required)?;
 
+    let concurrency = args.concurrency.unwrap_or(config.concurrency);
+    if concurrency == 0 {
+        bail!("Concurrency must be at least 1");
+    }
+
     let font_db = Arc::new({
         let mut db = fontdb::Database::new();
         db.load_system_fonts();
@@ -71,8 +154,22 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
 
     let (lockfile_tx, lockfile_rx) = mpsc::channel::<LockfileInsertion>(100);
 
+    let repacking_inputs = match &repack {
+        Some(scope) => scope.inputs(&config.inputs, |input| should_pack(input, &args)),
+        None => Vec::new(),
+    };
+
+    // A repack replaces every sprite entry of an input at once, so the old
+    // entries stay in the lockfile until all of the new pages have uploaded
+    let mut seed_lockfile = Lockfile::default();
+    for input_name in &repacking_inputs {
+        for (hash, entry) in lockfile.sprite_entries(input_name) {
+            seed_lockfile.insert(input_name, hash, entry.clone());
+        }
+    }
+
     let lockfile_handle =
-        tokio::spawn(async move { collect_lockfile_insertions(lockfile_rx).await });
+        tokio::spawn(async move { collect_lockfile_insertions(lockfile_rx, seed_lockfile).await });
 
     let (result_tx, result_rx) = mpsc::channel::<SyncResult>(100);
 
@@ -100,15 +197,82 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
 
         font_db,
 
-        client: WebApiClient::new(auth, config.creator, args.expected_price),
+        client: WebApiClient::new(
+            auth,
+            config.creator.clone(),
+            args.expected_price,
+            config.retry.clone(),
+            config.polling.clone(),
+        ),
+        concurrency,
+        update_strategies: config
+            .inputs
+            .iter()
+            .map(|(name, input)| (name.clone(), input.update_strategy))
+            .collect(),
+        update_fallbacks: config
+            .inputs
+            .iter()
+            .map(|(name, input)| (name.clone(), input.update_fallback))
+            .collect(),
     });
 
     let mut duplicate_assets = HashMap::<String, Vec<DuplicateFile>>::new();
 
+    let mut repacked = Vec::new();
+    let mut sequence_patterns = HashMap::<String, SequencePattern>::new();
+    let mut sprite_sheets = HashMap::<String, BTreeMap<RelativePathBuf, sheet::SpriteSheet>>::new();
+    let mut animations = HashMap::<String, Vec<AnimationFile>>::new();
+    let mut shared_atlases = BTreeMap::<String, SharedAtlas>::new();
+
     for (input_name, input) in &config.inputs {
-        let walk_results = walk::walk(state.clone(), input_name.clone(), input).await?;
+        let repacking = repacking_inputs.contains(input_name);
+
+        let packed_name = input.atlas.as_ref().unwrap_or(input_name);
+        if repacking && !repacked.contains(packed_name) {
+            repacked.push(packed_name.clone());
+        }
+
+        let walk_results = walk::walk(state.clone(), input_name.clone(), input, repacking).await?;
+
+        let pack_options = input_pack_options(&config, input, &args);
+        let incremental = pack_options
+            .as_ref()
+            .is_some_and(|options| options.incremental);
+
+        let sequence_pattern = pack_options
+            .as_ref()
+            .filter(|options| options.sequences)
+            .map(|options| SequencePattern::new(&options.sequence_pattern))
+            .transpose()?;
+
+        // Frames of a sequence share a page, so a changed frame brings the rest along
+        let changed_sequences: HashSet<String> = match &sequence_pattern {
+            Some(pattern) => walk_results
+                .iter()
+                .filter_map(|result| match result {
+                    WalkedFile::New(asset) => pattern.frame_of(asset.path.as_str()),
+                    _ => None,
+                })
+                .map(|frame| frame.sequence)
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        if let Some(pattern) = sequence_pattern.clone() {
+            sequence_patterns.insert(input_name.clone(), pattern);
+        }
+
+        let input_sheets = if input.spritesheets {
+            read_sprite_sheets(input, &walk_results).await
+        } else {
+            BTreeMap::new()
+        };
 
         let mut new_assets = Vec::with_capacity(walk_results.len());
+        let mut unpacked_paths: HashSet<RelativePathBuf> = input_sheets.keys().cloned().collect();
+        let mut kept_sprites = Vec::new();
+        let mut live_sprites = Vec::new();
         let mut dupe_count = 0;
 
         for result in walk_results {
@@ -117,6 +281,17 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
                     new_assets.push(asset);
                 }
                 WalkedFile::Existing(existing) => {
+                    let in_changed_sequence = existing.entry.sprite_info.is_some()
+                        && sequence_pattern
+                            .as_ref()
+                            .and_then(|pattern| pattern.frame_of(existing.path.as_str()))
+                            .is_some_and(|frame| changed_sequences.contains(&frame.sequence));
+
+                    if in_changed_sequence {
+                        new_assets.push(existing.asset);
+                        continue;
+                    }
+
                     if args.dry_run {
                         continue;
                     }
@@ -126,7 +301,15 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
                             .send(LockfileInsertion {
                                 input_name: input_name.clone(),
                                 hash: existing.hash,
-                                entry: existing.entry.clone(),
+                                record: LockfileRecord::Entry(LockfileEntry {
+                                    // Entries from older lockfiles don't have their path yet.
+                                    // Sprites point at their atlas page, so they never get one.
+                                    path: match existing.entry.sprite_info {
+                                        Some(_) => None,
+                                        None => Some(existing.path.clone()),
+                                    },
+                                    ..existing.entry.clone()
+                                }),
                                 // This takes too long, and we're not really losing anything here.
                                 write: false,
                             })
@@ -134,15 +317,15 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
                     }
 
                     let node = if let Some(ref sprite_info) = existing.entry.sprite_info {
-                        codegen::Node::AtlasSprite(codegen::AtlasSpriteData {
-                            image: format!("rbxassetid://{}", existing.entry.asset_id),
-                            rect: sprite_info.rect,
-                            size: sprite_info.source_size,
-                            trimmed: sprite_info.trimmed,
-                            sprite_source_size: sprite_info.sprite_source_size,
-                        })
+                        codegen::Node::AtlasSprite(codegen::AtlasSpriteData::from_sprite_info(
+                            &format!("rbxassetid://{}", existing.entry.asset_id),
+                            sprite_info,
+                        ))
                     } else {
-                        codegen::Node::String(format!("rbxassetid://{}", existing.entry.asset_id))
+                        image_node(
+                            format!("rbxassetid://{}", existing.entry.asset_id),
+                            existing.asset.slice_center,
+                        )
                     };
 
                     codegen_tx
@@ -152,9 +335,29 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
                             node,
                         })
                         .await?;
+
+                    if existing.entry.sprite_info.is_some() {
+                        live_sprites.push((existing.path.clone(), existing.entry.clone()));
+
+                        if incremental {
+                            kept_sprites.push((existing.asset, existing.entry));
+                        }
+                    }
+                }
+                WalkedFile::Layered(layered) => {
+                    if !input.psd.pack {
+                        unpacked_paths.extend(layered.layers);
+                    }
+                }
+                WalkedFile::Animation(animation) => {
+                    animations
+                        .entry(input_name.clone())
+                        .or_default()
+                        .push(animation);
                 }
                 WalkedFile::Duplicate(dupe) => {
-                    if input.warn_each_duplicate {
+                    // Themes that leave an SVG as it is are expected to match
+                    if input.warn_each_duplicate && !dupe.same_source {
                         warn!(
                             "Duplicate file found: {} (original at {})",
                             dupe.path, dupe.original_path
@@ -165,15 +368,14 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
                         continue;
                     }
 
-                    dupe_count += 1;
+                    if !dupe.same_source {
+                        dupe_count += 1;
+                    }
 
                     duplicate_assets
                         .entry(input_name.clone())
                         .or_default()
-                        .push(DuplicateFile {
-                            original_path: dupe.original_path,
-                            path: dupe.path,
-                        });
+                        .push(dupe);
                 }
             }
         }
@@ -217,40 +419,140 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
         )
         .await?;
 
+        // Spritesheets are already packed, so they're uploaded as they are,
+        // and so are PSD layers that are kept out of packing
+        let (unpacked_assets, processed_assets): (Vec<_>, Vec<_>) = processed_assets
+            .into_iter()
+            .partition(|asset| unpacked_paths.contains(&asset.path));
+
+        if !input_sheets.is_empty() {
+            sprite_sheets.insert(input_name.clone(), input_sheets);
+        }
+
+        let kept_sprites = process_kept_sprites(
+            kept_sprites,
+            state.clone(),
+            input_name.clone(),
+            input.bleed,
+            args.optimize,
+        )
+        .await?;
+
         // Handle packing if enabled
-        let final_assets = if should_pack(input, &args) {
-            let (assets, metadata) = handle_packing(
-                processed_assets,
-                state.clone(),
-                input_name.clone(),
-                input,
-                &args,
-            )
-            .await?;
+        let mut final_assets = match (pack_options, &input.atlas) {
+            // Images of inputs sharing an atlas are packed once every input is walked
+            (Some(_), Some(atlas)) => {
+                let (images, other_assets): (Vec<_>, Vec<_>) = processed_assets
+                    .into_iter()
+                    .partition(|asset| matches!(asset.ty, crate::asset::AssetType::Image(_)));
 
-            if let Some(metadata) = metadata {
-                packing_metadata
-                    .lock()
-                    .await
-                    .insert(input_name.clone(), metadata);
+                let shared = shared_atlases.entry(atlas.clone()).or_default();
+                shared.add(input_name, images, kept_sprites, live_sprites);
+
+                other_assets
             }
+            (Some(pack_options), None) => {
+                let (assets, metadata) = handle_packing(
+                    processed_assets,
+                    kept_sprites,
+                    &live_sprites,
+                    input_name,
+                    pack_options,
+                    Some(&input.output_path),
+                )
+                .await?;
 
-            assets
-        } else {
-            processed_assets
+                if let Some(mut metadata) = metadata {
+                    metadata.repacking = repacking;
+                    packing_metadata
+                        .lock()
+                        .await
+                        .insert(input_name.clone(), metadata);
+                }
+
+                assets
+            }
+            (None, _) => processed_assets,
         };
 
+        final_assets.extend(unpacked_assets);
+
         perform::perform(&final_assets, state.clone(), input_name.clone()).await?;
     }
 
+    for (atlas_name, shared) in shared_atlases {
+        let pack_options = atlas_pack_options(&config, &atlas_name, &args);
+        let (assets, metadata) = handle_packing(
+            shared.assets,
+            shared.kept,
+            &shared.live,
+            &atlas_name,
+            pack_options,
+            None,
+        )
+        .await?;
+
+        if let Some(mut metadata) = metadata {
+            metadata.sprite_owners = shared.owners.clone();
+            metadata.repacking = repacked.contains(&atlas_name);
+            packing_metadata
+                .lock()
+                .await
+                .insert(atlas_name.clone(), metadata);
+        }
+
+        // Sprites that couldn't be packed are uploaded by the input they came from
+        let mut pages = Vec::new();
+        let mut overflow = BTreeMap::<String, Vec<Asset>>::new();
+        for mut asset in assets {
+            match shared.owners.get(asset.path.as_str()) {
+                Some((input_name, path)) => {
+                    asset.path = path.clone();
+                    overflow.entry(input_name.clone()).or_default().push(asset);
+                }
+                None => pages.push(asset),
+            }
+        }
+
+        for (input_name, assets) in overflow {
+            perform::perform(&assets, state.clone(), input_name).await?;
+        }
+
+        perform::perform(&pages, state.clone(), atlas_name).await?;
+    }
+
     drop(state);
 
     result_handle.await??;
 
+    for packed_name in &repacked {
+        let metadata_guard = packing_metadata.lock().await;
+        let Some(metadata) = metadata_guard.get(packed_name) else {
+            continue;
+        };
+
+        let page_count = metadata.manifest.pages.len();
+        let uploaded = metadata.uploaded_pages.len();
+        if uploaded != page_count {
+            bail!(
+                "Repack of '{packed_name}' failed: only {uploaded} of {page_count} atlas page(s) were uploaded, so its sprite entries were left unchanged"
+            );
+        }
+
+        info!("Repacked '{packed_name}' into {page_count} atlas page(s)");
+    }
+
     drop(codegen_tx);
     drop(lockfile_tx);
 
-    let new_lockfile = lockfile_handle.await??;
+    let (mut new_lockfile, repacked_entries) = lockfile_handle.await??;
+    for input_name in &repacking_inputs {
+        new_lockfile.remove_sprite_entries(input_name);
+    }
+    for (input_name, hash, entry) in repacked_entries {
+        new_lockfile.insert(&input_name, &hash, entry);
+    }
+
     if matches!(args.target, SyncTarget::Cloud) {
         new_lockfile.write(None).await?;
     }
@@ -266,6 +568,62 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
         }
     }
 
+    for (input_name, sheets) in sprite_sheets {
+        let Some(source) = inputs_to_sources.get_mut(&input_name) else {
+            continue;
+        };
+
+        for (path, sheet) in sheets {
+            // Other nodes, like sliced images, are left as they are
+            let Some(codegen::Node::String(image)) = source.get(&path).cloned() else {
+                continue;
+            };
+            source.remove(&path);
+
+            // Frames are keyed under the sheet's name, e.g. hero/walk_0.png
+            let sheet_path = path.with_extension("");
+            for (frame_path, node) in sheet.nodes(&image) {
+                source.insert(sheet_path.join(frame_path), node);
+            }
+        }
+    }
+
+    for (input_name, input_animations) in animations {
+        let Some(source) = inputs_to_sources.get_mut(&input_name) else {
+            continue;
+        };
+
+        for animation in input_animations {
+            insert_animation_flipbooks(source, &animation);
+        }
+    }
+
+    for (input_name, pattern) in &sequence_patterns {
+        let Some(source) = inputs_to_sources.get_mut(input_name) else {
+            continue;
+        };
+
+        let input_prefix = config.inputs[input_name].path.get_prefix();
+
+        for (path, frames) in codegen::take_sequences(source, pattern) {
+            let fps = read_flipbook_fps(&input_prefix, &path).await?;
+            source.insert(
+                path,
+                codegen::Node::Flipbook(codegen::FlipbookData { frames, fps }),
+            );
+        }
+    }
+
+    for (input_name, source) in inputs_to_sources.iter_mut() {
+        if !config.inputs[input_name].variants {
+            continue;
+        }
+
+        for (path, variants) in codegen::take_variants(source) {
+            source.insert(path, codegen::Node::Variants(variants));
+        }
+    }
+
     let mut total_web_assets = 0;
     let mut total_codegen_files = 0;
 
@@ -316,6 +674,15 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
                 output_file.display(),
                 asset_count
             );
+
+            if codegen_config.variant_helper && input.variants {
+                let helper_file = input.output_path.join(format!("variants.{ext}"));
+                fs::write(&helper_file, codegen::generate_variant_helper(lang))
+                    .await
+                    .with_context(|| {
+                        format!("Failed to write variants module: {}", helper_file.display())
+                    })?;
+            }
         }
     }
 
@@ -328,10 +695,118 @@ pub async fn sync(multi_progress: MultiProgress, args: SyncArgs) -> Result<()> {
     Ok(())
 }
 
+/// Turn the frame tags of an Aseprite file into flipbooks next to its frames
+fn insert_animation_flipbooks(
+    source: &mut BTreeMap<RelativePathBuf, codegen::Node>,
+    animation: &AnimationFile,
+) {
+    let frames_dir = animation.path.with_extension("");
+
+    for tag in &animation.tags {
+        let frames: Option<Vec<codegen::AtlasSpriteData>> = tag
+            .frames
+            .iter()
+            .map(|&index| match source.get(&animation.frames[index]) {
+                Some(codegen::Node::AtlasSprite(data)) => Some(data.clone()),
+                _ => None,
+            })
+            .collect();
+
+        // Flipbooks are made of atlas sprites, so the frames have to be packed
+        let Some(frames) = frames else {
+            warn!(
+                "The frame tags of {} only become flipbooks when its input is packed",
+                animation.path
+            );
+            return;
+        };
+
+        let path = frames_dir.join(&tag.name);
+        if source.contains_key(&path) {
+            warn!(
+                "Frame tag '{}' of {} has the same name as one of its frames, it will be left out",
+                tag.name, animation.path
+            );
+            continue;
+        }
+
+        let fps = sheet::frame_rate(
+            tag.frames
+                .iter()
+                .map(|&index| Some(animation.durations[index])),
+        );
+
+        source.insert(
+            path,
+            codegen::Node::Flipbook(codegen::FlipbookData { frames, fps }),
+        );
+    }
+}
+
+/// Read the data files of the spritesheets among an input's images
+async fn read_sprite_sheets(
+    input: &Input,
+    walk_results: &[WalkedFile],
+) -> BTreeMap<RelativePathBuf, sheet::SpriteSheet> {
+    let input_prefix = input.path.get_prefix();
+    let mut sheets = BTreeMap::new();
+
+    for result in walk_results {
+        let asset = match result {
+            WalkedFile::New(asset) => asset,
+            WalkedFile::Existing(existing) => &existing.asset,
+            WalkedFile::Duplicate(_) | WalkedFile::Animation(_) | WalkedFile::Layered(_) => {
+                continue;
+            }
+        };
+
+        if !matches!(asset.ty, crate::asset::AssetType::Image(_)) {
+            continue;
+        }
+
+        match sheet::read_sheet(&input_prefix, &asset.path).await {
+            Ok(Some(sheet)) => {
+                sheets.insert(asset.path.clone(), sheet);
+            }
+            Ok(None) => {}
+            Err(err) => warn!("{err:#}, it will be synced as a regular image"),
+        }
+    }
+
+    sheets
+}
+
+#[derive(Deserialize)]
+struct FlipbookSidecar {
+    fps: Option<f64>,
+}
+
+/// Optional settings for a sequence, read from e.g. `walk.flipbook.json` for `walk_0.png`
+async fn read_flipbook_fps(input_prefix: &Path, sequence: &RelativePath) -> Result<Option<f64>> {
+    let path = sequence
+        .with_extension("flipbook.json")
+        .to_path(input_prefix);
+
+    let json = match fs::read_to_string(&path).await {
+        Ok(json) => json,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Failed to read flipbook sidecar: {}", path.display()));
+        }
+    };
+
+    let sidecar: FlipbookSidecar = serde_json::from_str(&json)
+        .with_context(|| format!("Failed to parse flipbook sidecar: {}", path.display()))?;
+
+    Ok(sidecar.fps)
+}
+
 pub struct SyncResult {
     hash: String,
     path: RelativePathBuf,
     input_name: String,
+    slice_center: Option<Rect>,
     backend: BackendSyncResult,
 }
 
@@ -342,6 +817,19 @@ async fn handle_sync_results(
     packing_metadata: Arc<tokio::sync::Mutex<HashMap<String, PackingMetadata>>>,
 ) -> anyhow::Result<()> {
     while let Some(result) = rx.recv().await {
+        if let BackendSyncResult::Pending(operation_id) = result.backend {
+            lockfile_tx
+                .send(LockfileInsertion {
+                    input_name: result.input_name,
+                    hash: result.hash,
+                    record: LockfileRecord::Pending(PendingOperation { operation_id }),
+                    write: true,
+                })
+                .await?;
+
+            continue;
+        }
+
         // Check if this is an atlas upload
         let is_atlas = result.path.extension().is_some_and(|ext| ext == "png")
             && result
@@ -351,6 +839,15 @@ async fn handle_sync_results(
 
         if let BackendSyncResult::Cloud(asset_id) = result.backend {
             if is_atlas {
+                lockfile_tx
+                    .send(LockfileInsertion {
+                        input_name: result.input_name.clone(),
+                        hash: result.hash.clone(),
+                        record: LockfileRecord::Settled,
+                        write: false,
+                    })
+                    .await?;
+
                 // Handle atlas upload - create AtlasSprite codegen entries
                 handle_atlas_upload(
                     &result,
@@ -366,10 +863,11 @@ async fn handle_sync_results(
                     .send(LockfileInsertion {
                         input_name: result.input_name.clone(),
                         hash: result.hash,
-                        entry: LockfileEntry {
+                        record: LockfileRecord::Entry(LockfileEntry {
                             asset_id,
+                            path: Some(result.path.clone()),
                             sprite_info: None,
-                        },
+                        }),
                         write: true,
                     })
                     .await?;
@@ -378,7 +876,7 @@ async fn handle_sync_results(
                     .send(CodegenInsertion {
                         input_name: result.input_name,
                         asset_path: result.path,
-                        node: codegen::Node::String(format!("rbxassetid://{asset_id}")),
+                        node: image_node(format!("rbxassetid://{asset_id}"), result.slice_center),
                     })
                     .await?;
             }
@@ -398,7 +896,7 @@ async fn handle_sync_results(
                     .send(CodegenInsertion {
                         input_name: result.input_name,
                         asset_path: result.path.clone(),
-                        node: codegen::Node::String(asset_id.clone()),
+                        node: image_node(asset_id.clone(), result.slice_center),
                     })
                     .await?;
             }
@@ -408,6 +906,17 @@ async fn handle_sync_results(
     Ok(())
 }
 
+/// The codegen node for an uploaded asset, with its slice center if it has one
+fn image_node(image: String, slice_center: Option<Rect>) -> codegen::Node {
+    match slice_center {
+        Some(slice_center) => codegen::Node::SlicedImage(codegen::SlicedImageData {
+            image,
+            slice_center,
+        }),
+        None => codegen::Node::String(image),
+    }
+}
+
 async fn handle_atlas_upload(
     result: &SyncResult,
     atlas_asset_url: String,
@@ -415,36 +924,45 @@ async fn handle_atlas_upload(
     lockfile_tx: &Sender<LockfileInsertion>,
     packing_metadata: &Arc<tokio::sync::Mutex<HashMap<String, PackingMetadata>>>,
 ) -> anyhow::Result<()> {
-    let metadata_guard = packing_metadata.lock().await;
-    let Some(metadata) = metadata_guard.get(&result.input_name) else {
+    let mut metadata_guard = packing_metadata.lock().await;
+    let Some(metadata) = metadata_guard.get_mut(&result.input_name) else {
         let input = &result.input_name;
         log::warn!("No packing metadata found for input '{input}'");
         return Ok(());
     };
 
-    // Extract page index from filename (format: "{input}-sheet-{N}.png")
+    // Pages are uploaded under the file name the manifest lists for them
     let filename = result.path.file_name().context("Invalid atlas filename")?;
 
-    let page_index = filename
-        .strip_suffix(".png")
-        .and_then(|s| s.rsplit_once("-sheet-"))
-        .and_then(|(_, idx)| idx.parse::<usize>().ok())
-        .context("Failed to extract page index from atlas filename")?;
+    let page = metadata
+        .manifest
+        .pages
+        .iter()
+        .find(|page| page.filename == filename)
+        .context("Failed to find the atlas page for this filename")?
+        .clone();
+
+    metadata.uploaded_pages.insert(page.filename.clone());
 
     // Find all sprites on this page and create AtlasSprite codegen entries
     for (sprite_name, sprite_info) in &metadata.manifest.sprites {
-        if sprite_info.page_index != page_index {
+        if !sprite_info.is_on(&page) {
             continue;
         }
 
-        let Some(original_path) = metadata.sprite_to_path.get(sprite_name).cloned() else {
-            log::warn!("No original path found for sprite '{sprite_name}'");
-            continue;
+        // Sprites are named by their path relative to the input, unless
+        // they're on a shared atlas
+        let (input_name, original_path) = match metadata.sprite_owners.get(sprite_name) {
+            Some((input_name, path)) => (input_name.clone(), path.clone()),
+            None => (
+                result.input_name.clone(),
+                RelativePathBuf::from(sprite_name),
+            ),
         };
 
         codegen_tx
             .send(CodegenInsertion {
-                input_name: result.input_name.clone(),
+                input_name: input_name.clone(),
                 asset_path: original_path,
                 node: codegen::Node::AtlasSprite(codegen::AtlasSpriteData {
                     image: atlas_asset_url.clone(),
@@ -452,6 +970,8 @@ async fn handle_atlas_upload(
                     size: sprite_info.source_size,
                     trimmed: sprite_info.trimmed,
                     sprite_source_size: sprite_info.sprite_source_size,
+                    rotated: sprite_info.rotated,
+                    slice_center: sprite_info.slice_center,
                 }),
             })
             .await?;
@@ -468,15 +988,29 @@ async fn handle_atlas_upload(
                 source_size: sprite_info.source_size,
                 trimmed: sprite_info.trimmed,
                 sprite_source_size: sprite_info.sprite_source_size,
+                rotated: sprite_info.rotated,
+                slice_center: sprite_info.slice_center,
+                page: Some(crate::lockfile::SpritePage {
+                    index: page.index,
+                    group: page.group.clone(),
+                    size: page.size,
+                }),
+            };
+
+            let entry = LockfileEntry {
+                asset_id,
+                path: None,
+                sprite_info: Some(lockfile_sprite_info),
             };
 
             lockfile_tx
                 .send(LockfileInsertion {
-                    input_name: result.input_name.clone(),
+                    input_name,
                     hash: sprite_hash,
-                    entry: LockfileEntry {
-                        asset_id,
-                        sprite_info: Some(lockfile_sprite_info),
+                    record: if metadata.repacking {
+                        LockfileRecord::Repacked(entry)
+                    } else {
+                        LockfileRecord::Entry(entry)
                     },
                     write: true,
                 })
@@ -530,23 +1064,52 @@ async fn collect_codegen_insertions(
 struct LockfileInsertion {
     input_name: String,
     hash: String,
-    entry: LockfileEntry,
+    record: LockfileRecord,
     write: bool,
 }
 
+enum LockfileRecord {
+    Entry(LockfileEntry),
+    /// An upload Roblox hasn't finished processing, replaced by its entry
+    /// once it has
+    Pending(PendingOperation),
+    /// An atlas page that finished uploading, whose sprites have entries of
+    /// their own
+    Settled,
+    /// A sprite entry of a repacked atlas, only applied once every page of
+    /// the repack has uploaded
+    Repacked(LockfileEntry),
+}
+
 async fn collect_lockfile_insertions(
     mut rx: Receiver<LockfileInsertion>,
-) -> anyhow::Result<Lockfile> {
-    let mut new_lockfile = Lockfile::default();
+    mut new_lockfile: Lockfile,
+) -> anyhow::Result<(Lockfile, Vec<(String, String, LockfileEntry)>)> {
+    let mut repacked_entries = Vec::new();
 
     while let Some(insertion) = rx.recv().await {
-        new_lockfile.insert(&insertion.input_name, &insertion.hash, insertion.entry);
+        match insertion.record {
+            LockfileRecord::Entry(entry) => {
+                new_lockfile.insert(&insertion.input_name, &insertion.hash, entry)
+            }
+            LockfileRecord::Pending(operation) => {
+                new_lockfile.insert_pending(&insertion.input_name, &insertion.hash, operation)
+            }
+            LockfileRecord::Settled => {
+                new_lockfile.remove_pending(&insertion.input_name, &insertion.hash)
+            }
+            LockfileRecord::Repacked(entry) => {
+                repacked_entries.push((insertion.input_name, insertion.hash, entry));
+                continue;
+            }
+        }
+
         if insertion.write {
             new_lockfile.write(None).await?;
         }
     }
 
-    Ok(new_lockfile)
+    Ok((new_lockfile, repacked_entries))
 }
 
 /// Check if packing should be enabled for this input
@@ -560,7 +1123,31 @@ fn should_pack(input: &Input, args: &SyncArgs) -> bool {
     }
 
     // Check input configuration
-    input.pack.as_ref().is_some_and(|pack| pack.enabled)
+    input.atlas.is_some() || input.pack.as_ref().is_some_and(|pack| pack.enabled)
+}
+
+/// The pack options of an input's shared atlas if it has one, or its own,
+/// with CLI overrides applied. `None` if the input isn't packed.
+fn input_pack_options(config: &Config, input: &Input, args: &SyncArgs) -> Option<PackOptions> {
+    if !should_pack(input, args) {
+        return None;
+    }
+
+    Some(match &input.atlas {
+        Some(atlas) => atlas_pack_options(config, atlas, args),
+        None => apply_pack_overrides(input.pack.as_ref(), args),
+    })
+}
+
+/// The pack options of a shared atlas with CLI overrides applied
+fn atlas_pack_options(config: &Config, atlas: &str, args: &SyncArgs) -> PackOptions {
+    // Inputs opting into a shared atlas are what enables it
+    let options = PackOptions {
+        enabled: true,
+        ..config.atlases[atlas].clone()
+    };
+
+    apply_pack_overrides(Some(&options), args)
 }
 
 /// Apply CLI argument overrides to pack options
@@ -586,41 +1173,298 @@ fn apply_pack_overrides(base_options: Option<&PackOptions>, args: &SyncArgs) ->
     if let Some(algorithm) = args.pack_algorithm.clone() {
         options.algorithm = algorithm;
     }
+    if let Some(heuristic) = args.pack_heuristic.clone() {
+        options.heuristic = heuristic;
+    }
+    if let Some(choice) = args.pack_free_rect_choice.clone() {
+        options.free_rect_choice = choice;
+    }
+    if let Some(split) = args.pack_split_rule.clone() {
+        options.split_rule = split;
+    }
     if args.pack_trim {
         options.allow_trim = true;
     }
     if args.pack_no_trim {
         options.allow_trim = false;
     }
+    if args.pack_no_shrink {
+        options.shrink_to_fit = false;
+    }
     if let Some(page_limit) = args.pack_page_limit {
         options.page_limit = Some(page_limit);
     }
+    if let Some(overflow) = args.pack_overflow.clone() {
+        options.overflow = overflow;
+    }
     if let Some(sort) = args.pack_sort.clone() {
         options.sort = sort;
     }
     if args.pack_dedupe {
         options.dedupe = true;
     }
+    if args.pack_rotate {
+        options.allow_rotation = true;
+    }
+    if args.pack_no_rotate {
+        options.allow_rotation = false;
+    }
+    if args.pack_incremental {
+        options.incremental = true;
+    }
 
     options
 }
 
+/// Write the atlas pages rendered this sync and the full layout of the input's
+/// pages to `dir`, removing the pages the previous manifest there listed that
+/// are no longer in use
+async fn write_atlases(
+    atlases: &[pack::Atlas],
+    layout: &pack::AtlasManifest,
+    dir: &Path,
+    input_name: &str,
+) -> Result<()> {
+    fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("Failed to create atlas directory: {}", dir.display()))?;
+
+    for atlas in atlases {
+        let page = layout
+            .page(atlas.group.as_deref(), atlas.page_index)
+            .context("Atlas page is missing from the manifest")?;
+
+        let page_path = dir.join(&page.filename);
+        fs::write(&page_path, &atlas.image_data)
+            .await
+            .with_context(|| format!("Failed to write atlas page: {}", page_path.display()))?;
+    }
+
+    let manifest_path = dir.join(format!("{input_name}.atlas.json"));
+    match fs::read_to_string(&manifest_path).await {
+        Ok(json) => match pack::AtlasManifest::from_json(&json) {
+            Ok(previous) => {
+                let unused_pages = previous.pages.iter().filter(|page| {
+                    !layout
+                        .pages
+                        .iter()
+                        .any(|current| current.filename == page.filename)
+                });
+
+                for page in unused_pages {
+                    let page_path = dir.join(&page.filename);
+                    match fs::remove_file(&page_path).await {
+                        Ok(()) => debug!("Removed unused atlas page {}", page_path.display()),
+                        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(err) => {
+                            return Err(err).with_context(|| {
+                                format!(
+                                    "Failed to remove unused atlas page: {}",
+                                    page_path.display()
+                                )
+                            });
+                        }
+                    }
+                }
+            }
+            Err(err) => warn!(
+                "Replacing unreadable atlas manifest {}: {err}",
+                manifest_path.display()
+            ),
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err).with_context(|| {
+                format!("Failed to read atlas manifest: {}", manifest_path.display())
+            });
+        }
+    }
+
+    fs::write(&manifest_path, layout.to_json()?)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to write atlas manifest: {}",
+                manifest_path.display()
+            )
+        })?;
+
+    info!(
+        "Wrote {} atlas page(s) and manifest to {}",
+        atlases.len(),
+        dir.display()
+    );
+
+    Ok(())
+}
+
 struct PackingMetadata {
     manifest: pack::manifest::AtlasManifest,
-    sprite_to_path: HashMap<String, RelativePathBuf>,
     sprite_to_hash: HashMap<String, String>,
+    /// For shared atlases, the input and path each sprite name stands for
+    sprite_owners: HashMap<String, (String, RelativePathBuf)>,
+    /// The file names of the pages that finished uploading
+    uploaded_pages: HashSet<String>,
+    /// Whether this is a repack, whose sprite entries are held back until
+    /// every page has uploaded
+    repacking: bool,
+}
+
+/// The images of the inputs packed into a shared atlas, named by their input
+/// and path (e.g. `icons/close.png`) so they don't clash
+#[derive(Default)]
+struct SharedAtlas {
+    assets: Vec<Asset>,
+    kept: Vec<(Asset, LockfileEntry)>,
+    live: Vec<(RelativePathBuf, LockfileEntry)>,
+    owners: HashMap<String, (String, RelativePathBuf)>,
+}
+
+impl SharedAtlas {
+    fn add(
+        &mut self,
+        input_name: &str,
+        assets: Vec<Asset>,
+        kept: Vec<(Asset, LockfileEntry)>,
+        live: Vec<(RelativePathBuf, LockfileEntry)>,
+    ) {
+        for asset in assets {
+            let asset = self.rename(input_name, asset);
+            self.assets.push(asset);
+        }
+
+        for (asset, entry) in kept {
+            let asset = self.rename(input_name, asset);
+            self.kept.push((asset, entry));
+        }
+
+        for (path, entry) in live {
+            self.live
+                .push((RelativePath::new(input_name).join(path), entry));
+        }
+    }
+
+    fn rename(&mut self, input_name: &str, mut asset: Asset) -> Asset {
+        let name = RelativePath::new(input_name).join(&asset.path);
+        self.owners
+            .insert(name.to_string(), (input_name.to_string(), asset.path));
+        asset.path = name;
+        asset
+    }
+}
+
+/// Process the sprites kept from previous syncs, since they're redrawn when
+/// new sprites land on their page
+async fn process_kept_sprites(
+    kept: Vec<(Asset, LockfileEntry)>,
+    state: Arc<SyncState>,
+    input_name: String,
+    bleed: bool,
+    optimize: bool,
+) -> anyhow::Result<Vec<(Asset, LockfileEntry)>> {
+    if kept.is_empty() {
+        return Ok(kept);
+    }
+
+    let (assets, entries): (Vec<_>, Vec<_>) = kept
+        .into_iter()
+        .map(|(asset, entry)| {
+            let path = asset.path.clone();
+            (asset, (path, entry))
+        })
+        .unzip();
+    let mut entries: HashMap<RelativePathBuf, LockfileEntry> = entries.into_iter().collect();
+
+    let assets = process::process(assets, state, input_name, bleed, optimize).await?;
+
+    Ok(assets
+        .into_iter()
+        .filter_map(|asset| {
+            let entry = entries.remove(&asset.path)?;
+            Some((asset, entry))
+        })
+        .collect())
+}
+
+/// The layout of the sprites still in use from previous syncs, from the page
+/// each one's lockfile entry says it was packed onto
+///
+/// Sprites packed before pages were recorded are left out, so they're packed
+/// again like new ones.
+fn previous_layout(
+    input_name: &str,
+    sprites: &[(RelativePathBuf, LockfileEntry)],
+) -> pack::AtlasManifest {
+    let mut pages = BTreeMap::<(Option<String>, usize), (pack::Size, usize)>::new();
+    let mut manifest = pack::AtlasManifest::new(input_name.to_string());
+
+    for (path, entry) in sprites {
+        let Some(sprite_info) = &entry.sprite_info else {
+            continue;
+        };
+
+        let Some(page) = &sprite_info.page else {
+            log::debug!("Sprite '{path}' has no recorded atlas page and will be repacked");
+            continue;
+        };
+
+        pages
+            .entry((page.group.clone(), page.index))
+            .or_insert((page.size, 0))
+            .1 += 1;
+
+        manifest.add_sprite(pack::SpriteInfo {
+            name: path.to_string(),
+            rect: sprite_info.rect,
+            source_size: sprite_info.source_size,
+            trimmed: sprite_info.trimmed,
+            sprite_source_size: sprite_info.sprite_source_size,
+            rotated: sprite_info.rotated,
+            page_index: page.index,
+            group: page.group.clone(),
+            slice_center: sprite_info.slice_center,
+        });
+    }
+
+    for ((group, index), (size, sprite_count)) in pages {
+        let sheet_name = pack::sheet_name(input_name, group.as_deref());
+        manifest.add_page(pack::PageInfo {
+            index,
+            filename: pack::page_filename(&sheet_name, index),
+            group,
+            size,
+            sprite_count,
+        });
+    }
+
+    manifest
 }
 
 /// Handle packing of assets into atlases
+///
+/// `kept` are unchanged sprites from previous syncs; with incremental packing they
+/// keep their place and are only redrawn if new sprites land on their page.
+/// `live` are the lockfile entries of every unchanged sprite, whose pages stay
+/// in use alongside the new ones.
 async fn handle_packing(
     assets: Vec<Asset>,
-    _state: Arc<SyncState>,
-    input_name: String,
-    input: &Input,
-    args: &SyncArgs,
+    kept: Vec<(Asset, LockfileEntry)>,
+    live: &[(RelativePathBuf, LockfileEntry)],
+    input_name: &str,
+    pack_options: PackOptions,
+    output_path: Option<&Path>,
 ) -> anyhow::Result<(Vec<Asset>, Option<PackingMetadata>)> {
-    let pack_options = apply_pack_overrides(input.pack.as_ref(), args);
+    let atlas_dir = pack_options
+        .write_atlases
+        .then(|| {
+            pack_options
+                .atlas_dir
+                .clone()
+                .or_else(|| output_path.map(Path::to_path_buf))
+        })
+        .flatten();
     let packer = Packer::new(pack_options);
+    let previous = previous_layout(input_name, live);
 
     // Filter only image assets for packing
     let (packable_assets, non_packable_assets): (Vec<_>, Vec<_>) = assets
@@ -631,6 +1475,16 @@ async fn handle_packing(
         info!(
             "No image files to pack for input '{input_name}' (web assets will still be included in codegen if configured)"
         );
+
+        // Sprites may have been removed from the previous pages
+        if let Some(atlas_dir) = atlas_dir
+            && !previous.pages.is_empty()
+        {
+            let mut layout = previous;
+            layout.set_options_hash(packer.options_hash());
+            write_atlases(&[], &layout, &atlas_dir, input_name).await?;
+        }
+
         return Ok((non_packable_assets, None));
     }
 
@@ -640,30 +1494,71 @@ async fn handle_packing(
         input_name
     );
 
-    // Build sprite name to original path and hash mapping
-    let mut sprite_to_path = HashMap::new();
-    let mut sprite_to_hash = HashMap::new();
-    for asset in &packable_assets {
-        if let Some(name) = asset.path.file_stem() {
-            sprite_to_path.insert(name.to_string(), asset.path.clone());
-            sprite_to_hash.insert(name.to_string(), asset.hash.clone());
-        }
+    // Build sprite name (its input-relative path) to hash mapping
+    let mut sprite_to_hash: HashMap<String, String> = packable_assets
+        .iter()
+        .map(|asset| (asset.path.to_string(), asset.hash.clone()))
+        .collect();
+
+    let pack_result = if kept.is_empty() {
+        packer.pack_assets_after(&packable_assets, &previous, input_name)?
+    } else {
+        let kept_assets: Vec<Asset> = kept.into_iter().map(|(asset, _)| asset).collect();
+
+        sprite_to_hash.extend(
+            kept_assets
+                .iter()
+                .map(|asset| (asset.path.to_string(), asset.hash.clone())),
+        );
+
+        packer.pack_incremental(&packable_assets, &kept_assets, &previous, input_name)?
+    };
+
+    // Sprites that couldn't be packed go through the regular per-asset upload path
+    let mut result_assets = non_packable_assets;
+    if !pack_result.overflow.is_empty() {
+        let overflow: HashSet<&str> = pack_result.overflow.iter().map(String::as_str).collect();
+        let overflow_assets = packable_assets
+            .into_iter()
+            .filter(|asset| overflow.contains(asset.path.as_str()));
+
+        result_assets.extend(overflow_assets);
+
+        info!(
+            "{} image(s) for input '{input_name}' will be uploaded individually",
+            overflow.len()
+        );
     }
 
-    let pack_result = packer.pack_assets(&packable_assets, &input_name)?;
+    // The new pages replace the previous pages with the same file name, and
+    // the rest of the previous pages are still in use
+    if let Some(atlas_dir) = atlas_dir {
+        let layout = previous.merge(pack_result.manifest.clone());
+        write_atlases(&pack_result.atlases, &layout, &atlas_dir, input_name).await?;
+    }
 
     if pack_result.atlases.is_empty() {
         warn!("No atlases were generated for input '{input_name}'");
-        return Ok((non_packable_assets, None));
+        return Ok((result_assets, None));
     }
 
-    let mut result_assets = non_packable_assets;
     let atlas_count = pack_result.atlases.len();
 
     // Convert atlases to assets (keep in memory, will be uploaded by backend)
     for atlas in &pack_result.atlases {
-        let filename = format!("{}-sheet-{}.png", input_name, atlas.page_index);
-        let sync_path = RelativePathBuf::from(filename);
+        debug!(
+            "Atlas page {} is {}x{} with {} sprite(s)",
+            atlas.page_index,
+            atlas.size.width,
+            atlas.size.height,
+            atlas.sprites.len()
+        );
+
+        let page = pack_result
+            .manifest
+            .page(atlas.group.as_deref(), atlas.page_index)
+            .context("Atlas page is missing from the manifest")?;
+        let sync_path = RelativePathBuf::from(page.filename.as_str());
         let atlas_asset = Asset::new(sync_path, atlas.image_data.clone())?;
         result_assets.push(atlas_asset);
     }
@@ -672,9 +1567,67 @@ async fn handle_packing(
 
     let metadata = PackingMetadata {
         manifest: pack_result.manifest,
-        sprite_to_path,
         sprite_to_hash,
+        sprite_owners: HashMap::new(),
+        uploaded_pages: HashSet::new(),
+        repacking: false,
     };
 
     Ok((result_assets, Some(metadata)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_inputs() -> HashMap<String, Input> {
+        toml::from_str(
+            r#"
+            [icons]
+            path = "icons/**/*"
+            output_path = "src"
+            atlas = "ui"
+
+            [buttons]
+            path = "buttons/**/*"
+            output_path = "src"
+            atlas = "ui"
+
+            [sprites]
+            path = "sprites/**/*"
+            output_path = "src"
+            pack = { enabled = true }
+
+            [sounds]
+            path = "sounds/**/*"
+            output_path = "src"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn is_packed(input: &Input) -> bool {
+        input.atlas.is_some() || input.pack.as_ref().is_some_and(|pack| pack.enabled)
+    }
+
+    #[test]
+    fn test_repack_scope_includes_inputs_sharing_an_atlas() {
+        let inputs = make_inputs();
+
+        let scope = RepackScope {
+            input: Some("icons".to_string()),
+        };
+        assert_eq!(scope.inputs(&inputs, is_packed), ["buttons", "icons"]);
+
+        let scope = RepackScope {
+            input: Some("sprites".to_string()),
+        };
+        assert_eq!(scope.inputs(&inputs, is_packed), ["sprites"]);
+
+        let scope = RepackScope { input: None };
+        assert_eq!(
+            scope.inputs(&inputs, is_packed),
+            ["buttons", "icons", "sprites"]
+        );
+    }
+}
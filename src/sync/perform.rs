@@ -3,6 +3,7 @@ use super::{
     backend::{SyncBackend, cloud::CloudBackend, debug::DebugBackend, studio::StudioBackend},
 };
 use crate::{asset::Asset, cli::SyncTarget, progress_bar::ProgressBar, sync::SyncResult};
+use futures::stream::{self, StreamExt};
 use log::warn;
 use std::sync::Arc;
 
@@ -19,23 +20,29 @@ pub async fn perform(
         assets.len(),
     );
 
-    for asset in assets {
-        let input_name = input_name.clone();
+    // Uploads spend most of their time waiting on Roblox, so several are in
+    // flight at once
+    let mut results = stream::iter(assets)
+        .map(|asset| {
+            let state = state.clone();
+            let input_name = input_name.clone();
+            let backend = &backend;
 
-        let file_name = asset.path.to_string();
-        pb.set_msg(&file_name);
+            async move {
+                let res = match backend {
+                    TargetBackend::Debug(backend) => backend.sync(state, input_name, asset).await,
+                    TargetBackend::Cloud(backend) => backend.sync(state, input_name, asset).await,
+                    TargetBackend::Studio(backend) => backend.sync(state, input_name, asset).await,
+                };
 
-        let res = match backend {
-            TargetBackend::Debug(ref backend) => {
-                backend.sync(state.clone(), input_name.clone(), asset).await
-            }
-            TargetBackend::Cloud(ref backend) => {
-                backend.sync(state.clone(), input_name.clone(), asset).await
-            }
-            TargetBackend::Studio(ref backend) => {
-                backend.sync(state.clone(), input_name.clone(), asset).await
+                (asset, res)
             }
-        };
+        })
+        .buffer_unordered(state.concurrency);
+
+    while let Some((asset, res)) = results.next().await {
+        let file_name = asset.path.to_string();
+        pb.set_msg(&file_name);
 
         match res {
             Ok(Some(result)) => {
@@ -45,6 +52,7 @@ pub async fn perform(
                         input_name: input_name.clone(),
                         hash: asset.hash.clone(),
                         path: asset.path.clone(),
+                        slice_center: asset.slice_center,
                         backend: result,
                     })
                     .await?;
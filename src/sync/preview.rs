@@ -0,0 +1,180 @@
+use super::{
+    SharedAtlas, SyncState, atlas_pack_options, input_pack_options, process, read_sprite_sheets,
+    walk::{self, WalkedFile},
+};
+use crate::{
+    asset::{Asset, AssetType},
+    auth::Auth,
+    cli::{PackPreviewArgs, SyncArgs, SyncTarget},
+    config::{Config, Input, PackOptions},
+    lockfile::Lockfile,
+    pack::{Packer, preview},
+    web_api::WebApiClient,
+};
+use anyhow::{Context, Result, bail};
+use indicatif::MultiProgress;
+use log::info;
+use resvg::usvg::fontdb;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+use tokio::{fs, sync::mpsc};
+
+pub async fn pack_preview(multi_progress: MultiProgress, args: PackPreviewArgs) -> Result<()> {
+    let config = Config::read().await?;
+
+    if let Some(name) = &args.input {
+        let input = config
+            .inputs
+            .get(name)
+            .with_context(|| format!("No input named '{name}' found in configuration"))?;
+
+        if input_pack_options(&config, input, &args.sync).is_none() {
+            bail!("Input '{name}' does not have packing enabled, so there is nothing to preview");
+        }
+    }
+
+    let font_db = Arc::new({
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        db
+    });
+
+    // Nothing is synced, so every file is read as if it were new
+    let (result_tx, _result_rx) = mpsc::channel(1);
+    let state = Arc::new(SyncState {
+        args: SyncArgs {
+            target: SyncTarget::Debug,
+            ..args.sync.clone()
+        },
+
+        existing_lockfile: Lockfile::default(),
+        result_tx,
+
+        multi_progress,
+
+        font_db: font_db.clone(),
+
+        client: WebApiClient::new(
+            Auth::new(None, false)?,
+            config.creator.clone(),
+            args.sync.expected_price,
+            config.retry.clone(),
+            config.polling.clone(),
+        ),
+        concurrency: 1,
+        update_strategies: HashMap::new(),
+        update_fallbacks: HashMap::new(),
+    });
+
+    let mut input_names: Vec<&String> = config.inputs.keys().collect();
+    input_names.sort();
+
+    let mut sheets = Vec::<(String, PackOptions, Vec<Asset>)>::new();
+    let mut shared_atlases = BTreeMap::<String, SharedAtlas>::new();
+
+    for input_name in input_names {
+        if args.input.as_ref().is_some_and(|name| name != input_name) {
+            continue;
+        }
+
+        let input = &config.inputs[input_name];
+        let Some(pack_options) = input_pack_options(&config, input, &args.sync) else {
+            continue;
+        };
+
+        let images = packable_images(state.clone(), input_name, input).await?;
+
+        match &input.atlas {
+            Some(atlas) => shared_atlases.entry(atlas.clone()).or_default().add(
+                input_name,
+                images,
+                Vec::new(),
+                Vec::new(),
+            ),
+            None => sheets.push((input_name.clone(), pack_options, images)),
+        }
+    }
+
+    for (atlas_name, shared) in shared_atlases {
+        let pack_options = atlas_pack_options(&config, &atlas_name, &args.sync);
+        sheets.push((atlas_name, pack_options, shared.assets));
+    }
+
+    fs::create_dir_all(&args.output)
+        .await
+        .with_context(|| format!("Failed to create directory: {}", args.output.display()))?;
+
+    let mut reports = Vec::new();
+
+    for (name, pack_options, assets) in sheets {
+        let result = Packer::new(pack_options).pack_assets(&assets, &name)?;
+
+        for atlas in &result.atlases {
+            let page = result
+                .manifest
+                .page(atlas.group.as_deref(), atlas.page_index)
+                .context("Atlas page is missing from the manifest")?;
+
+            let page_path = args.output.join(&page.filename);
+            fs::write(&page_path, &atlas.image_data)
+                .await
+                .with_context(|| format!("Failed to write atlas page: {}", page_path.display()))?;
+
+            let preview_path = args.output.join(preview::preview_filename(&page.filename));
+            let preview = preview::render_page(atlas, font_db.clone())?;
+            fs::write(&preview_path, preview).await.with_context(|| {
+                format!("Failed to write page preview: {}", preview_path.display())
+            })?;
+        }
+
+        reports.push(preview::SheetReport::new(&name, &result)?);
+    }
+
+    let index_path = args.output.join("index.html");
+    fs::write(&index_path, preview::index_html(&reports))
+        .await
+        .with_context(|| format!("Failed to write preview index: {}", index_path.display()))?;
+
+    info!("Wrote pack preview to {}", index_path.display());
+
+    Ok(())
+}
+
+/// The processed images of an input that a sync would pack
+async fn packable_images(
+    state: Arc<SyncState>,
+    input_name: &str,
+    input: &Input,
+) -> Result<Vec<Asset>> {
+    let walk_results = walk::walk(state.clone(), input_name.to_string(), input, false).await?;
+
+    // Spritesheets and PSD layers kept out of packing are uploaded as they are
+    let mut unpacked_paths: HashSet<_> = if input.spritesheets {
+        read_sprite_sheets(input, &walk_results)
+            .await
+            .into_keys()
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let mut images = Vec::new();
+    for result in walk_results {
+        match result {
+            WalkedFile::New(asset) if matches!(asset.ty, AssetType::Image(_)) => {
+                images.push(asset);
+            }
+            WalkedFile::Layered(layered) if !input.psd.pack => {
+                unpacked_paths.extend(layered.layers);
+            }
+            _ => {}
+        }
+    }
+
+    images.retain(|asset| !unpacked_paths.contains(&asset.path));
+
+    let optimize = state.args.optimize;
+    process::process(images, state, input_name.to_string(), input.bleed, optimize).await
+}
@@ -0,0 +1,376 @@
+use super::codegen::{AtlasSpriteData, FlipbookData, Node};
+use crate::{
+    lockfile::SpriteInfo,
+    pack::rect::{Rect, Size},
+};
+use anyhow::Context;
+use relative_path::{RelativePath, RelativePathBuf};
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, Visitor},
+};
+use std::{collections::BTreeMap, fmt, path::Path};
+use tokio::fs;
+
+/// A finished spritesheet exported by TexturePacker or Aseprite
+#[derive(Debug)]
+pub struct SpriteSheet {
+    /// Frames in the order the data file lists them
+    pub frames: Vec<SheetFrame>,
+    /// Aseprite frame tags, which become flipbook sequences
+    pub tags: Vec<FrameTag>,
+}
+
+#[derive(Debug)]
+pub struct SheetFrame {
+    pub name: String,
+    pub info: SpriteInfo,
+    /// How long the frame is shown in milliseconds, if the data file says
+    pub duration: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct FrameTag {
+    pub name: String,
+    /// Indices into the sheet's frames, in playback order
+    pub frames: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct RawSheet {
+    frames: RawFrames,
+    #[serde(default)]
+    meta: RawMeta,
+}
+
+/// TexturePacker's "JSON (Array)" and "JSON (Hash)" formats, both of which
+/// Aseprite can export too
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawFrames {
+    Array(Vec<RawFrame>),
+    Hash(OrderedFrames),
+}
+
+/// Frames keyed by name, in the order they appear in the file
+struct OrderedFrames(Vec<(String, RawFrame)>);
+
+impl<'de> Deserialize<'de> for OrderedFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedFramesVisitor;
+
+        impl<'de> Visitor<'de> for OrderedFramesVisitor {
+            type Value = OrderedFrames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of frame names to frames")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    frames.push(entry);
+                }
+                Ok(OrderedFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedFramesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawFrame {
+    filename: Option<String>,
+    frame: RawRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Option<RawRect>,
+    source_size: Option<RawSize>,
+    duration: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct RawRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct RawSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct RawMeta {
+    #[serde(default)]
+    frame_tags: Vec<RawTag>,
+}
+
+#[derive(Deserialize)]
+struct RawTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+}
+
+impl SpriteSheet {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let raw: RawSheet = serde_json::from_str(json)?;
+
+        let raw_frames = match raw.frames {
+            RawFrames::Array(frames) => frames
+                .into_iter()
+                .enumerate()
+                .map(|(i, frame)| {
+                    (
+                        frame.filename.clone().unwrap_or_else(|| i.to_string()),
+                        frame,
+                    )
+                })
+                .collect(),
+            RawFrames::Hash(OrderedFrames(frames)) => frames,
+        };
+
+        let frames: Vec<SheetFrame> = raw_frames
+            .into_iter()
+            .map(|(name, frame)| SheetFrame {
+                name,
+                duration: frame.duration,
+                info: frame.into_sprite_info(),
+            })
+            .collect();
+
+        let tags = raw
+            .meta
+            .frame_tags
+            .into_iter()
+            .map(|tag| FrameTag::new(tag.name, tag.from, tag.to, &tag.direction, frames.len()))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { frames, tags })
+    }
+
+    /// The codegen nodes for the sheet's frames and tags, keyed relative to the sheet
+    pub fn nodes(&self, image: &str) -> BTreeMap<RelativePathBuf, Node> {
+        let sprite = |frame: &SheetFrame| AtlasSpriteData::from_sprite_info(image, &frame.info);
+
+        let mut nodes: BTreeMap<RelativePathBuf, Node> = self
+            .frames
+            .iter()
+            .map(|frame| {
+                (
+                    RelativePathBuf::from(&frame.name),
+                    Node::AtlasSprite(sprite(frame)),
+                )
+            })
+            .collect();
+
+        for tag in &self.tags {
+            let path = RelativePathBuf::from(&tag.name);
+            if nodes.contains_key(&path) {
+                log::warn!(
+                    "Frame tag '{}' has the same name as a frame, it will be left out",
+                    tag.name
+                );
+                continue;
+            }
+
+            let frames: Vec<&SheetFrame> = tag.frames.iter().map(|&i| &self.frames[i]).collect();
+
+            let fps = frame_rate(frames.iter().map(|frame| frame.duration));
+
+            nodes.insert(
+                path,
+                Node::Flipbook(FlipbookData {
+                    frames: frames.into_iter().map(sprite).collect(),
+                    fps,
+                }),
+            );
+        }
+
+        nodes
+    }
+}
+
+impl FrameTag {
+    /// A tag over the frames `from..=to`, played in the given Aseprite direction
+    pub fn new(
+        name: String,
+        from: usize,
+        to: usize,
+        direction: &str,
+        frame_count: usize,
+    ) -> anyhow::Result<Self> {
+        if from > to || to >= frame_count {
+            anyhow::bail!(
+                "Frame tag '{name}' spans frames {from} to {to}, but there are {frame_count} frame(s)"
+            );
+        }
+
+        let forward = from..=to;
+        let frames = match direction {
+            "reverse" => forward.rev().collect(),
+            // Play forward, then back without repeating either end
+            "pingpong" => forward
+                .clone()
+                .chain(forward.rev().skip(1).take((to - from).saturating_sub(1)))
+                .collect(),
+            _ => forward.collect(),
+        };
+
+        Ok(Self { name, frames })
+    }
+}
+
+/// The frame rate of frames shown for the given durations in milliseconds,
+/// if they all last equally long
+pub fn frame_rate(mut durations: impl Iterator<Item = Option<u32>>) -> Option<f64> {
+    let duration = durations.next()??;
+
+    (duration > 0 && durations.all(|other| other == Some(duration)))
+        .then(|| 1000.0 / duration as f64)
+}
+
+impl RawFrame {
+    fn into_sprite_info(self) -> SpriteInfo {
+        // Rotated frames list their upright size, but take up the swapped size on the sheet
+        let size = Size::new(self.frame.w, self.frame.h);
+        let placed_size = if self.rotated { size.rotated() } else { size };
+
+        SpriteInfo {
+            rect: Rect::new(
+                self.frame.x,
+                self.frame.y,
+                placed_size.width,
+                placed_size.height,
+            ),
+            source_size: self
+                .source_size
+                .map_or(size, |source| Size::new(source.w, source.h)),
+            trimmed: self.trimmed,
+            sprite_source_size: self
+                .sprite_source_size
+                .filter(|_| self.trimmed)
+                .map(|rect| Rect::new(rect.x, rect.y, rect.w, rect.h)),
+            rotated: self.rotated,
+            slice_center: None,
+            page: None,
+        }
+    }
+}
+
+/// Read the data file next to a sheet image, e.g. `hero.json` for `hero.png`
+pub async fn read_sheet(
+    input_prefix: &Path,
+    image_path: &RelativePath,
+) -> anyhow::Result<Option<SpriteSheet>> {
+    let path = image_path.with_extension("json").to_path(input_prefix);
+
+    let json = match fs::read_to_string(&path).await {
+        Ok(json) => json,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Failed to read spritesheet data: {}", path.display()));
+        }
+    };
+
+    let sheet = SpriteSheet::from_json(&json)
+        .with_context(|| format!("Failed to parse spritesheet data: {}", path.display()))?;
+
+    Ok(Some(sheet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texture_packer_hash() {
+        let json = r#"{
+            "frames": {
+                "b.png": {
+                    "frame": {"x": 0, "y": 0, "w": 16, "h": 32},
+                    "rotated": true,
+                    "trimmed": false,
+                    "spriteSourceSize": {"x": 0, "y": 0, "w": 16, "h": 32},
+                    "sourceSize": {"w": 16, "h": 32}
+                },
+                "a.png": {
+                    "frame": {"x": 32, "y": 0, "w": 10, "h": 12},
+                    "rotated": false,
+                    "trimmed": true,
+                    "spriteSourceSize": {"x": 3, "y": 2, "w": 10, "h": 12},
+                    "sourceSize": {"w": 16, "h": 16}
+                }
+            },
+            "meta": {"app": "https://www.codeandweb.com/texturepacker"}
+        }"#;
+
+        let sheet = SpriteSheet::from_json(json).unwrap();
+        assert_eq!(sheet.frames[0].name, "b.png");
+        assert_eq!(sheet.frames[0].info.rect, Rect::new(0, 0, 32, 16));
+        assert!(sheet.frames[0].info.rotated);
+        assert_eq!(sheet.frames[0].info.sprite_source_size, None);
+
+        let trimmed = &sheet.frames[1].info;
+        assert_eq!(trimmed.source_size, Size::new(16, 16));
+        assert_eq!(trimmed.sprite_source_size, Some(Rect::new(3, 2, 10, 12)));
+        assert!(sheet.tags.is_empty());
+    }
+
+    #[test]
+    fn test_aseprite_tags_become_flipbooks() {
+        let frame = |x: u32| {
+            format!(
+                r#"{{"filename": "hero {x}.aseprite", "frame": {{"x": {}, "y": 0, "w": 8, "h": 8}}, "duration": 100}}"#,
+                x * 8
+            )
+        };
+        let json = format!(
+            r#"{{
+                "frames": [{}, {}, {}],
+                "meta": {{"frameTags": [
+                    {{"name": "idle", "from": 0, "to": 0, "direction": "forward"}},
+                    {{"name": "walk", "from": 0, "to": 2, "direction": "pingpong"}}
+                ]}}
+            }}"#,
+            frame(0),
+            frame(1),
+            frame(2)
+        );
+
+        let sheet = SpriteSheet::from_json(&json).unwrap();
+        assert_eq!(sheet.tags[1].frames, vec![0, 1, 2, 1]);
+
+        let nodes = sheet.nodes("rbxassetid://1");
+        assert_eq!(nodes.len(), 5);
+
+        let Some(Node::Flipbook(walk)) = nodes.get(RelativePath::new("walk")) else {
+            panic!("walk should be a flipbook");
+        };
+        assert_eq!(walk.frames.len(), 4);
+        assert_eq!(walk.fps, Some(10.0));
+        assert_eq!(walk.frames[3].rect, Rect::new(8, 0, 8, 8));
+    }
+
+    #[test]
+    fn test_tag_out_of_range() {
+        let json = r#"{
+            "frames": [{"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}],
+            "meta": {"frameTags": [{"name": "walk", "from": 0, "to": 3}]}
+        }"#;
+
+        assert!(SpriteSheet::from_json(json).is_err());
+    }
+}
@@ -1,15 +1,30 @@
-use super::SyncState;
+use super::{SyncState, sheet::FrameTag};
 use crate::{
-    asset::Asset, cli::SyncTarget, config::Input, lockfile::LockfileEntry,
+    asset::{Asset, AssetType},
+    cli::SyncTarget,
+    config::{Input, SvgOptions, SvgTheme},
+    lockfile::LockfileEntry,
+    pack::rect::Rect,
     progress_bar::ProgressBar,
+    util::{
+        aseprite, psd,
+        slice::{self, SliceInsets},
+        svg::{self, SvgRaster},
+    },
 };
 use anyhow::Context;
 use dashmap::DashMap;
 use fs_err::tokio as fs;
 use futures::stream::{self, StreamExt};
-use log::debug;
-use relative_path::{PathExt, RelativePathBuf};
-use std::{path::PathBuf, sync::Arc};
+use log::{debug, warn};
+use regex::Regex;
+use relative_path::{PathExt, RelativePath, RelativePathBuf};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::task::spawn_blocking;
 use walkdir::WalkDir;
 
@@ -18,14 +33,19 @@ struct WalkCtx {
     state: Arc<SyncState>,
     input_name: String,
     input_prefix: PathBuf,
-    seen_hashes: Arc<DashMap<String, PathBuf>>,
+    seen_hashes: Arc<DashMap<String, RelativePathBuf>>,
     pb: ProgressBar,
+    repack: bool,
+    psd_group_pattern: Option<Regex>,
+    svg_options: SvgOptions,
+    themes: Arc<BTreeMap<String, SvgTheme>>,
 }
 
 pub async fn walk(
     state: Arc<SyncState>,
     input_name: String,
     input: &Input,
+    repack: bool,
 ) -> anyhow::Result<Vec<WalkedFile>> {
     let input_prefix = input.path.get_prefix();
 
@@ -43,7 +63,17 @@ pub async fn walk(
         total_files,
     );
 
-    let seen_hashes = Arc::new(DashMap::<String, PathBuf>::with_capacity(total_files));
+    let psd_group_pattern = input
+        .psd
+        .group_pattern
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .context("Invalid PSD group pattern")?;
+
+    let seen_hashes = Arc::new(DashMap::<String, RelativePathBuf>::with_capacity(
+        total_files,
+    ));
 
     let ctx = WalkCtx {
         state,
@@ -51,6 +81,10 @@ pub async fn walk(
         seen_hashes,
         pb,
         input_prefix,
+        repack,
+        psd_group_pattern,
+        svg_options: input.svg.clone(),
+        themes: Arc::new(input.themes.clone()),
     };
 
     let results = stream::iter(entries)
@@ -63,73 +97,392 @@ pub async fn walk(
                 ctx.pb.inc(1);
 
                 match result {
-                    Ok(res) => Some(res),
+                    Ok(res) => res,
                     Err(err) => {
                         debug!("Skipping file {}: {:?}", path.display(), err);
-                        None
+                        Vec::new()
                     }
                 }
             }
         })
         .buffer_unordered(100)
-        .filter_map(|result| async move { result })
         .collect::<Vec<_>>()
         .await;
 
     ctx.pb.finish();
 
-    Ok(results)
+    Ok(results.into_iter().flatten().collect())
 }
 
 pub struct ExistingFile {
     pub path: RelativePathBuf,
     pub hash: String,
     pub entry: LockfileEntry,
+    /// Kept around so unchanged sprites can be redrawn onto their atlas page
+    pub asset: Asset,
 }
 
 pub struct DuplicateFile {
     pub path: RelativePathBuf,
     pub original_path: RelativePathBuf,
+    /// Whether both are themes of the same SVG, which render the same when
+    /// the themes don't recolor it
+    pub same_source: bool,
+}
+
+/// An Aseprite file whose frames were walked as separate images
+pub struct AnimationFile {
+    pub path: RelativePathBuf,
+    /// The path of each frame's image
+    pub frames: Vec<RelativePathBuf>,
+    /// How long each frame is shown in milliseconds
+    pub durations: Vec<u32>,
+    pub tags: Vec<FrameTag>,
+}
+
+/// A PSD file whose layers were walked as separate images
+pub struct LayeredFile {
+    /// The path of each layer's image
+    pub layers: Vec<RelativePathBuf>,
 }
 
 pub enum WalkedFile {
     New(Asset),
     Existing(ExistingFile),
     Duplicate(DuplicateFile),
+    Animation(AnimationFile),
+    Layered(LayeredFile),
 }
 
-async fn walk_file(ctx: &WalkCtx, path: PathBuf) -> anyhow::Result<WalkedFile> {
+async fn walk_file(ctx: &WalkCtx, path: PathBuf) -> anyhow::Result<Vec<WalkedFile>> {
     let data = fs::read(&path).await?;
     let rel_path = path.relative_to(&ctx.input_prefix)?;
 
-    let rel_path_clone = rel_path.clone();
-    let asset = spawn_blocking(move || Asset::new(rel_path_clone, data))
+    if matches!(rel_path.extension(), Some("aseprite" | "ase")) {
+        return walk_aseprite(ctx, rel_path, data).await;
+    }
+
+    if rel_path.extension() == Some("psd") {
+        return walk_psd(ctx, rel_path, data).await;
+    }
+
+    if rel_path.extension() == Some("svg") {
+        return walk_svg(ctx, &path, rel_path, data).await;
+    }
+
+    let mut asset = spawn_blocking(move || Asset::new(rel_path, data))
         .await
         .context("Failed to create asset")??;
 
-    if let Some(seen_path) = ctx.seen_hashes.get(&asset.hash) {
-        let rel_seen_path = seen_path.relative_to(&ctx.input_prefix)?;
+    match read_sidecar::<SliceInsets>(&path, slice::sidecar_name, "slice").await {
+        Ok(Some(insets)) => match slice_center(&asset, insets) {
+            Ok(center) => asset.set_slice_center(center),
+            Err(err) => warn!("Ignoring slice insets for {}: {err:#}", asset.path),
+        },
+        Ok(None) => {}
+        Err(err) => warn!("{err:#}"),
+    }
+
+    Ok(vec![walk_asset(ctx, asset)])
+}
+
+/// Split an Aseprite file into an image for each frame, named by its index
+/// under the file's name (e.g. `hero/0.png`)
+async fn walk_aseprite(
+    ctx: &WalkCtx,
+    path: RelativePathBuf,
+    data: Vec<u8>,
+) -> anyhow::Result<Vec<WalkedFile>> {
+    let decoded = spawn_blocking(move || aseprite::decode(&data))
+        .await
+        .context("Failed to decode Aseprite file")??;
+
+    let frames_dir = path.with_extension("");
+    let mut walked = Vec::with_capacity(decoded.frames.len() + 1);
+    let mut frames = Vec::with_capacity(decoded.frames.len());
+    let mut durations = Vec::with_capacity(decoded.frames.len());
+
+    for (index, frame) in decoded.frames.into_iter().enumerate() {
+        let frame_path = frames_dir.join(format!("{index}.png"));
+        let asset = Asset::new(frame_path.clone(), frame.data)?;
+
+        walked.push(walk_asset(ctx, asset));
+        frames.push(frame_path);
+        durations.push(frame.duration);
+    }
+
+    let tags = decoded
+        .tags
+        .into_iter()
+        .map(|tag| FrameTag::new(tag.name, tag.from, tag.to, tag.direction, frames.len()))
+        .collect::<anyhow::Result<_>>()?;
+
+    walked.push(WalkedFile::Animation(AnimationFile {
+        path,
+        frames,
+        durations,
+        tags,
+    }));
+
+    Ok(walked)
+}
+
+/// Split a PSD file into an image for each top-level layer, named by the
+/// layer under the file's name (e.g. `mockup/Button.png`)
+async fn walk_psd(
+    ctx: &WalkCtx,
+    path: RelativePathBuf,
+    data: Vec<u8>,
+) -> anyhow::Result<Vec<WalkedFile>> {
+    let group_pattern = ctx.psd_group_pattern.clone();
+    let images = spawn_blocking(move || psd::layers(&data, group_pattern.as_ref()))
+        .await
+        .context("Failed to read PSD file")??;
+
+    let layers_dir = path.with_extension("");
+    let mut walked = Vec::with_capacity(images.len() + 1);
+    let mut layers = Vec::with_capacity(images.len());
+
+    for image in images {
+        // Layer names can have slashes, which would nest them further
+        let name = image.name.replace(['/', '\\'], "_");
+        let layer_path = layers_dir.join(format!("{name}.png"));
+        let asset = Asset::new(layer_path.clone(), image.data)?;
+
+        walked.push(walk_asset(ctx, asset));
+        layers.push(layer_path);
+    }
+
+    walked.push(WalkedFile::Layered(LayeredFile { layers }));
+
+    Ok(walked)
+}
+
+/// Rasterize an SVG file at each size its options ask for, with any extra
+/// sizes named as resolution variants of the file (e.g. `icon@2x.svg`), and
+/// in each of the input's themes under the theme's name (e.g. `dark/icon.svg`)
+async fn walk_svg(
+    ctx: &WalkCtx,
+    path: &Path,
+    rel_path: RelativePathBuf,
+    data: Vec<u8>,
+) -> anyhow::Result<Vec<WalkedFile>> {
+    let sidecar = read_sidecar::<SvgOptions>(path, svg::sidecar_name, "raster")
+        .await
+        .and_then(|options| {
+            if let Some(options) = &options {
+                options
+                    .validate()
+                    .with_context(|| format!("Ignoring raster sidecar for {rel_path}"))?;
+            }
+
+            Ok(options)
+        });
+
+    let options = match sidecar {
+        Ok(Some(options)) => options,
+        Ok(None) => ctx.svg_options.clone(),
+        Err(err) => {
+            warn!("{err:#}");
+            ctx.svg_options.clone()
+        }
+    };
+
+    let themed = if ctx.themes.is_empty() {
+        vec![(rel_path, data)]
+    } else {
+        ctx.themes
+            .iter()
+            .map(|(name, theme)| {
+                let recolored = svg::recolor(&data, &theme.colors)?;
+                Ok((RelativePath::new(name).join(&rel_path), recolored))
+            })
+            .collect::<anyhow::Result<_>>()?
+    };
+
+    let mut walked = Vec::new();
+    let mut rendered_paths = Vec::new();
+
+    for (themed_path, data) in themed {
+        for (raster_path, raster) in svg_rasters(&themed_path, &options) {
+            let mut asset = Asset::new(raster_path.clone(), data.clone())?;
 
-        return Ok(WalkedFile::Duplicate(DuplicateFile {
-            path: rel_path.clone(),
-            original_path: rel_seen_path,
-        }));
+            // Files rendered at their intrinsic size keep the hash of their data
+            if !raster.is_intrinsic() {
+                asset.set_svg_raster(raster);
+            }
+
+            let mut result = walk_asset(ctx, asset);
+
+            if let WalkedFile::Duplicate(dupe) = &mut result {
+                dupe.same_source = rendered_paths.contains(&dupe.original_path);
+            }
+
+            rendered_paths.push(raster_path);
+            walked.push(result);
+        }
+    }
+
+    Ok(walked)
+}
+
+/// The path and raster size of each image an SVG file is rendered as
+fn svg_rasters(path: &RelativePath, options: &SvgOptions) -> Vec<(RelativePathBuf, SvgRaster)> {
+    let mut sizes = options.sizes.clone();
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    let Some(&base) = sizes.first() else {
+        let raster = SvgRaster {
+            width: options.width,
+            height: options.height,
+            scale: options.scale,
+            dpi: options.dpi,
+        };
+
+        return vec![(path.to_owned(), raster)];
+    };
+
+    let stem = path.file_stem().unwrap_or_default();
+
+    sizes
+        .into_iter()
+        .map(|size| {
+            let raster = SvgRaster {
+                width: Some(size),
+                dpi: options.dpi,
+                ..Default::default()
+            };
+
+            if size == base {
+                return (path.to_owned(), raster);
+            }
+
+            // Sizes that aren't whole multiples of the smallest get a rounded
+            // scale, since it ends up in file and codegen names
+            let scale = format!("{:.2}", size as f64 / base as f64);
+            let scale = scale.trim_end_matches('0').trim_end_matches('.');
+            (path.with_file_name(format!("{stem}@{scale}x.svg")), raster)
+        })
+        .collect()
+}
+
+fn walk_asset(ctx: &WalkCtx, asset: Asset) -> WalkedFile {
+    if let Some(seen_path) = ctx.seen_hashes.get(&asset.hash) {
+        return WalkedFile::Duplicate(DuplicateFile {
+            path: asset.path.clone(),
+            original_path: seen_path.clone(),
+            same_source: false,
+        });
     }
 
-    ctx.seen_hashes.insert(asset.hash.clone(), path.clone());
+    ctx.seen_hashes
+        .insert(asset.hash.clone(), asset.path.clone());
 
     let entry = ctx
         .state
         .existing_lockfile
         .get(&ctx.input_name, &asset.hash);
 
+    // When repacking, sprites are treated as new so they get packed from scratch
+    let entry = entry.filter(|entry| !(ctx.repack && entry.sprite_info.is_some()));
+
     match (entry, &ctx.state.args.target) {
-        (Some(entry), SyncTarget::Cloud) => Ok(WalkedFile::Existing(ExistingFile {
-            path: rel_path,
+        (Some(entry), SyncTarget::Cloud) => WalkedFile::Existing(ExistingFile {
+            path: asset.path.clone(),
             hash: asset.hash.clone(),
             entry: entry.clone(),
-        })),
-        (Some(_), SyncTarget::Studio | SyncTarget::Debug) => Ok(WalkedFile::New(asset)),
-        (None, _) => Ok(WalkedFile::New(asset)),
+            asset,
+        }),
+        (Some(_), SyncTarget::Studio | SyncTarget::Debug) => WalkedFile::New(asset),
+        (None, _) => WalkedFile::New(asset),
+    }
+}
+
+/// The path and scale a resolution variant such as `icon@2x.png` stands in
+/// for, e.g. `icon.png` at a scale of 2
+pub fn variant_of(path: &RelativePath) -> Option<(RelativePathBuf, f64)> {
+    let stem = path.file_stem()?;
+    let (name, suffix) = stem.rsplit_once('@')?;
+
+    let scale: f64 = suffix.strip_suffix('x')?.parse().ok()?;
+    if name.is_empty() || !scale.is_finite() || scale <= 0.0 {
+        return None;
+    }
+
+    let file_name = match path.extension() {
+        Some(ext) => format!("{name}.{ext}"),
+        None => name.to_string(),
+    };
+
+    Some((path.with_file_name(file_name), scale))
+}
+
+/// Read a sidecar file next to an asset, if it has one
+async fn read_sidecar<T: DeserializeOwned>(
+    path: &Path,
+    sidecar_name: fn(&str) -> String,
+    kind: &str,
+) -> anyhow::Result<Option<T>> {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(None);
+    };
+
+    let sidecar_path = path.with_file_name(sidecar_name(file_name));
+
+    let json = match fs::read_to_string(&sidecar_path).await {
+        Ok(json) => json,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| {
+                format!("Failed to read {kind} sidecar: {}", sidecar_path.display())
+            });
+        }
+    };
+
+    let value = serde_json::from_str(&json)
+        .with_context(|| format!("Failed to parse {kind} sidecar: {}", sidecar_path.display()))?;
+
+    Ok(Some(value))
+}
+
+fn slice_center(asset: &Asset, insets: SliceInsets) -> anyhow::Result<Rect> {
+    if !matches!(asset.ty, AssetType::Image(_)) || asset.ext == "svg" {
+        anyhow::bail!("slicing is only supported for raster images");
+    }
+
+    insets.center(slice::image_size(&asset.data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_sizes_become_variants() {
+        let options = SvgOptions {
+            sizes: vec![64, 32, 96],
+            ..Default::default()
+        };
+
+        let rasters = svg_rasters(RelativePath::new("ui/icon.svg"), &options);
+        let paths: Vec<_> = rasters.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["ui/icon.svg", "ui/icon@2x.svg", "ui/icon@3x.svg"]);
+        assert_eq!(rasters[2].1.width, Some(96));
+
+        let (variant, scale) = variant_of(&rasters[1].0).unwrap();
+        assert_eq!((variant.as_str(), scale), ("ui/icon.svg", 2.0));
+    }
+
+    #[test]
+    fn test_svg_size_scales_are_rounded() {
+        let options = SvgOptions {
+            sizes: vec![24, 36, 64],
+            ..Default::default()
+        };
+
+        let rasters = svg_rasters(RelativePath::new("icon.svg"), &options);
+        let paths: Vec<_> = rasters.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["icon.svg", "icon@1.5x.svg", "icon@2.67x.svg"]);
     }
 }
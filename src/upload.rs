@@ -1,4 +1,10 @@
-use crate::{asset::Asset, auth::Auth, cli::UploadArgs, config::Creator, web_api::WebApiClient};
+use crate::{
+    asset::Asset,
+    auth::Auth,
+    cli::UploadArgs,
+    config::{Creator, PollOptions, RetryOptions},
+    web_api::WebApiClient,
+};
 use fs_err::tokio as fs;
 use relative_path::PathExt;
 use resvg::usvg::fontdb::Database;
@@ -21,7 +27,13 @@ pub async fn upload(args: UploadArgs) -> anyhow::Result<()> {
     };
     let auth = Auth::new(args.api_key, true)?;
 
-    let client = WebApiClient::new(auth, creator, args.expected_price);
+    let client = WebApiClient::new(
+        auth,
+        creator,
+        args.expected_price,
+        RetryOptions::default(),
+        PollOptions::default(),
+    );
 
     let asset_id = client.upload(&asset).await?;
 
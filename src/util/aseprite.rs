@@ -0,0 +1,166 @@
+use anyhow::Context;
+use asefile::{AnimationDirection, AsepriteFile};
+use image::RgbaImage;
+use std::io::Cursor;
+
+/// An Aseprite file with its visible layers flattened into each frame
+pub struct Aseprite {
+    pub frames: Vec<AsepriteFrame>,
+    pub tags: Vec<AsepriteTag>,
+}
+
+pub struct AsepriteFrame {
+    /// The frame encoded as a PNG
+    pub data: Vec<u8>,
+    /// How long the frame is shown in milliseconds
+    pub duration: u32,
+}
+
+pub struct AsepriteTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    /// The direction as Aseprite names it in its JSON exports
+    pub direction: &'static str,
+}
+
+pub fn decode(data: &[u8]) -> anyhow::Result<Aseprite> {
+    let file = AsepriteFile::read(data).context("Failed to read Aseprite file")?;
+
+    let frames = (0..file.num_frames())
+        .map(|index| {
+            let frame = file.frame(index);
+
+            // asefile uses its own version of the image crate
+            let image = frame.image();
+            let (width, height) = image.dimensions();
+            let image = RgbaImage::from_raw(width, height, image.into_raw())
+                .context("Aseprite frame has an invalid size")?;
+
+            let mut writer = Cursor::new(Vec::new());
+            image.write_to(&mut writer, image::ImageFormat::Png)?;
+
+            Ok(AsepriteFrame {
+                data: writer.into_inner(),
+                duration: frame.duration(),
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let tags = (0..file.num_tags())
+        .map(|index| {
+            let tag = file.tag(index);
+
+            AsepriteTag {
+                name: tag.name().to_string(),
+                from: tag.from_frame() as usize,
+                to: tag.to_frame() as usize,
+                direction: match tag.animation_direction() {
+                    AnimationDirection::Forward => "forward",
+                    AnimationDirection::Reverse => "reverse",
+                    AnimationDirection::PingPong => "pingpong",
+                },
+            }
+        })
+        .collect();
+
+    Ok(Aseprite { frames, tags })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(ty: u16, data: &[u8]) -> Vec<u8> {
+        let mut chunk = Vec::new();
+        chunk.extend(((data.len() + 6) as u32).to_le_bytes());
+        chunk.extend(ty.to_le_bytes());
+        chunk.extend(data);
+        chunk
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u16).to_le_bytes().to_vec();
+        bytes.extend(value.as_bytes());
+        bytes
+    }
+
+    /// A 2x2 RGBA file with one layer, a frame per color and a tag over all of them
+    fn make_file(colors: &[[u8; 4]], duration: u16) -> Vec<u8> {
+        let mut frames = Vec::new();
+
+        for (index, color) in colors.iter().enumerate() {
+            let mut chunks = Vec::new();
+
+            if index == 0 {
+                let mut layer = Vec::new();
+                layer.extend(1u16.to_le_bytes()); // Visible
+                layer.extend([0u8; 10]); // Type, child level, default size, blend mode
+                layer.extend([255, 0, 0, 0]); // Opacity
+                layer.extend(string("Layer"));
+                chunks.push(chunk(0x2004, &layer));
+
+                let mut tags = Vec::new();
+                tags.extend(1u16.to_le_bytes());
+                tags.extend([0u8; 8]);
+                tags.extend(0u16.to_le_bytes());
+                tags.extend((colors.len() as u16 - 1).to_le_bytes());
+                tags.push(0); // Forward
+                tags.extend([0u8; 12]); // Repeat, reserved, color
+                tags.extend(string("walk"));
+                chunks.push(chunk(0x2018, &tags));
+            }
+
+            let mut cel = Vec::new();
+            cel.extend([0u8; 6]); // Layer index, position
+            cel.push(255); // Opacity
+            cel.extend([0u8; 9]); // Raw cel type, z-index, reserved
+            cel.extend(2u16.to_le_bytes());
+            cel.extend(2u16.to_le_bytes());
+            for _ in 0..4 {
+                cel.extend(color);
+            }
+            chunks.push(chunk(0x2005, &cel));
+
+            let body: Vec<u8> = chunks.concat();
+            frames.extend(((body.len() + 16) as u32).to_le_bytes());
+            frames.extend(0xF1FAu16.to_le_bytes());
+            frames.extend((chunks.len() as u16).to_le_bytes());
+            frames.extend(duration.to_le_bytes());
+            frames.extend([0u8; 2]);
+            frames.extend((chunks.len() as u32).to_le_bytes());
+            frames.extend(body);
+        }
+
+        let mut header = Vec::new();
+        header.extend(((frames.len() + 128) as u32).to_le_bytes());
+        header.extend(0xA5E0u16.to_le_bytes());
+        header.extend((colors.len() as u16).to_le_bytes());
+        header.extend(2u16.to_le_bytes());
+        header.extend(2u16.to_le_bytes());
+        header.extend(32u16.to_le_bytes());
+        header.extend(1u32.to_le_bytes()); // Layer opacity is valid
+        header.resize(128, 0);
+
+        [header, frames].concat()
+    }
+
+    #[test]
+    fn test_decode_frames_and_tags() {
+        let data = make_file(&[[255, 0, 0, 255], [0, 0, 255, 255]], 50);
+        let decoded = decode(&data).unwrap();
+
+        assert_eq!(decoded.frames.len(), 2);
+        assert_eq!(decoded.frames[1].duration, 50);
+
+        let frame = image::load_from_memory(&decoded.frames[1].data)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(frame.dimensions(), (2, 2));
+        assert_eq!(frame.get_pixel(1, 1).0, [0, 0, 255, 255]);
+
+        let tag = &decoded.tags[0];
+        assert_eq!((tag.name.as_str(), tag.from, tag.to), ("walk", 0, 1));
+        assert_eq!(tag.direction, "forward");
+    }
+}
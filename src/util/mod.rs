@@ -1,4 +1,7 @@
 pub mod alpha_bleed;
 pub mod animation;
+pub mod aseprite;
 pub mod optimize;
+pub mod psd;
+pub mod slice;
 pub mod svg;
@@ -0,0 +1,227 @@
+use anyhow::{Context, bail};
+use image::{RgbaImage, imageops};
+use psd::{Psd, PsdLayer};
+use regex::Regex;
+use std::{collections::HashSet, io::Cursor};
+
+/// A top-level layer or group of a PSD, cropped to its bounds
+pub struct PsdImage {
+    pub name: String,
+    /// The image encoded as a PNG
+    pub data: Vec<u8>,
+}
+
+/// The PSD flattened into a single PNG, as Photoshop composited it
+pub fn flatten(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let psd = read(data)?;
+    encode(to_image(&psd, psd.rgba())?)
+}
+
+/// Export each visible top-level layer as its own image, along with the
+/// top-level groups whose name matches `group_pattern` (or every group when
+/// there is no pattern) flattened into one image. Layers in other groups are
+/// left out.
+pub fn layers(data: &[u8], group_pattern: Option<&Regex>) -> anyhow::Result<Vec<PsdImage>> {
+    let psd = read(data)?;
+    let mut images = Vec::new();
+    let mut names = HashSet::new();
+
+    let mut export = |name: &str, pixels: Vec<u8>| -> anyhow::Result<()> {
+        if !names.insert(name.to_string()) {
+            log::warn!("More than one PSD layer is named '{name}', only the first is exported");
+            return Ok(());
+        }
+
+        let Some(image) = crop_to_content(to_image(&psd, pixels)?) else {
+            log::debug!("Skipping empty PSD layer '{name}'");
+            return Ok(());
+        };
+
+        images.push(PsdImage {
+            name: name.to_string(),
+            data: encode(image)?,
+        });
+
+        Ok(())
+    };
+
+    for (index, layer) in psd.layers().iter().enumerate() {
+        if layer.parent_id().is_none() && layer.visible() {
+            let pixels = psd.flatten_layers_rgba(&|(i, _)| i == index)?;
+            export(layer.name(), pixels)?;
+        }
+    }
+
+    for id in psd.group_ids_in_order() {
+        let group = &psd.groups()[id];
+        if group.parent_id().is_some() || !group.visible() {
+            continue;
+        }
+
+        if group_pattern.is_some_and(|pattern| !pattern.is_match(group.name())) {
+            log::debug!("Skipping PSD group '{}'", group.name());
+            continue;
+        }
+
+        let pixels = psd.flatten_layers_rgba(&|(_, layer)| in_group(&psd, layer, *id))?;
+        export(group.name(), pixels)?;
+    }
+
+    Ok(images)
+}
+
+fn read(data: &[u8]) -> anyhow::Result<Psd> {
+    Psd::from_bytes(data).map_err(|err| anyhow::anyhow!("Failed to read PSD file: {err}"))
+}
+
+fn in_group(psd: &Psd, layer: &PsdLayer, group_id: u32) -> bool {
+    let mut parent = layer.parent_id();
+
+    while let Some(id) = parent {
+        if id == group_id {
+            return true;
+        }
+
+        parent = psd.groups().get(&id).and_then(|group| group.parent_id());
+    }
+
+    false
+}
+
+fn to_image(psd: &Psd, pixels: Vec<u8>) -> anyhow::Result<RgbaImage> {
+    RgbaImage::from_raw(psd.width(), psd.height(), pixels).context("PSD layer has an invalid size")
+}
+
+/// Crop an image to the bounds of its non-transparent pixels
+fn crop_to_content(image: RgbaImage) -> Option<RgbaImage> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel.0[3] == 0 {
+            continue;
+        }
+
+        bounds = Some(match bounds {
+            Some((min_x, min_y, max_x, max_y)) => {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+
+    let (min_x, min_y, max_x, max_y) = bounds?;
+
+    Some(imageops::crop_imm(&image, min_x, min_y, max_x - min_x + 1, max_y - min_y + 1).to_image())
+}
+
+fn encode(image: RgbaImage) -> anyhow::Result<Vec<u8>> {
+    if image.width() == 0 || image.height() == 0 {
+        bail!("PSD image is empty");
+    }
+
+    let mut writer = Cursor::new(Vec::new());
+    image.write_to(&mut writer, image::ImageFormat::Png)?;
+    Ok(writer.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Layer {
+        name: &'static str,
+        /// Top, left, bottom and right, with the bottom and right exclusive
+        bounds: (i32, i32, i32, i32),
+        color: [u8; 4],
+    }
+
+    /// An uncompressed RGB PSD with the given layers, bottom layer first
+    fn make_psd(width: u32, height: u32, layers: &[Layer]) -> Vec<u8> {
+        let mut psd = Vec::new();
+        psd.extend(b"8BPS");
+        psd.extend(1u16.to_be_bytes());
+        psd.extend([0u8; 6]);
+        psd.extend(4u16.to_be_bytes());
+        psd.extend(height.to_be_bytes());
+        psd.extend(width.to_be_bytes());
+        psd.extend(8u16.to_be_bytes());
+        psd.extend(3u16.to_be_bytes()); // RGB
+        psd.extend(0u32.to_be_bytes()); // Color mode data
+        psd.extend(0u32.to_be_bytes()); // Image resources
+
+        let mut records = Vec::new();
+        let mut channel_data = Vec::new();
+
+        for layer in layers {
+            let (top, left, bottom, right) = layer.bounds;
+            let pixel_count = ((bottom - top) * (right - left)) as usize;
+
+            records.extend(top.to_be_bytes());
+            records.extend(left.to_be_bytes());
+            records.extend(bottom.to_be_bytes());
+            records.extend(right.to_be_bytes());
+            records.extend(4u16.to_be_bytes());
+            for (id, value) in [(0i16, 0), (1, 1), (2, 2), (-1, 3)] {
+                records.extend(id.to_be_bytes());
+                records.extend((pixel_count as u32 + 2).to_be_bytes());
+
+                channel_data.extend(0u16.to_be_bytes()); // Raw
+                channel_data.extend(std::iter::repeat_n(layer.color[value], pixel_count));
+            }
+            records.extend(b"8BIMnorm");
+            records.extend([255, 0, 0b10, 0]); // Opacity, clipping, visible, filler
+
+            let mut name = vec![layer.name.len() as u8];
+            name.extend(layer.name.as_bytes());
+            name.resize(name.len().div_ceil(4) * 4, 0);
+
+            records.extend((8 + name.len() as u32).to_be_bytes());
+            records.extend(0u32.to_be_bytes()); // Layer mask
+            records.extend(0u32.to_be_bytes()); // Blending ranges
+            records.extend(name);
+        }
+
+        let mut layer_info = (layers.len() as i16).to_be_bytes().to_vec();
+        layer_info.extend(records);
+        layer_info.extend(channel_data);
+        layer_info.resize(layer_info.len().div_ceil(2) * 2, 0);
+
+        psd.extend((layer_info.len() as u32 + 8).to_be_bytes());
+        psd.extend((layer_info.len() as u32).to_be_bytes());
+        psd.extend(layer_info);
+        psd.extend(0u32.to_be_bytes()); // Global layer mask
+
+        psd.extend(0u16.to_be_bytes()); // Raw composite
+        psd.extend(std::iter::repeat_n(0u8, (width * height * 4) as usize));
+
+        psd
+    }
+
+    #[test]
+    fn test_layers_are_cropped_to_their_bounds() {
+        let data = make_psd(
+            8,
+            8,
+            &[
+                Layer {
+                    name: "Background",
+                    bounds: (0, 0, 8, 8),
+                    color: [0, 0, 255, 255],
+                },
+                Layer {
+                    name: "Button",
+                    bounds: (2, 1, 5, 7),
+                    color: [255, 0, 0, 255],
+                },
+            ],
+        );
+
+        let images = layers(&data, None).unwrap();
+        assert_eq!(images.len(), 2);
+
+        let button = images.iter().find(|image| image.name == "Button").unwrap();
+        let button = image::load_from_memory(&button.data).unwrap().to_rgba8();
+        assert_eq!(button.dimensions(), (6, 3));
+        assert_eq!(button.get_pixel(0, 0).0, [255, 0, 0, 255]);
+    }
+}
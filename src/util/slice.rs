@@ -0,0 +1,161 @@
+use crate::pack::rect::{Rect, Size};
+use anyhow::{Context, bail};
+use image::RgbaImage;
+use relative_path::{RelativePath, RelativePathBuf};
+use serde::Deserialize;
+use std::io::Cursor;
+
+/// Nine-slice insets in pixels, measured from each edge of the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SliceInsets {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl SliceInsets {
+    /// The rect left in the middle of an image of the given size, as used
+    /// by `SliceCenter`
+    pub fn center(&self, size: Size) -> anyhow::Result<Rect> {
+        if self.left + self.right >= size.width || self.top + self.bottom >= size.height {
+            bail!(
+                "Slice insets ({}, {}, {}, {}) leave no center in a {}x{} image",
+                self.left,
+                self.top,
+                self.right,
+                self.bottom,
+                size.width,
+                size.height
+            );
+        }
+
+        Ok(Rect::new(
+            self.left,
+            self.top,
+            size.width - self.left - self.right,
+            size.height - self.top - self.bottom,
+        ))
+    }
+}
+
+/// The sidecar file slice insets are read from, e.g. `button.png.slice.json`
+pub fn sidecar_name(file_name: &str) -> String {
+    format!("{file_name}.slice.json")
+}
+
+/// The path a `.9.png` image is known by once its markers are stripped
+pub fn nine_patch_path(path: &RelativePath) -> Option<RelativePathBuf> {
+    let file_name = path.file_name()?;
+    let stem = file_name.strip_suffix(".9.png")?;
+
+    Some(path.with_file_name(format!("{stem}.png")))
+}
+
+/// Read the stretch markers in the one pixel border of a nine-patch image,
+/// returning the image without its border and the center the markers span
+pub fn strip_nine_patch(data: &[u8]) -> anyhow::Result<(Vec<u8>, Rect)> {
+    let image = image::load_from_memory(data)?.to_rgba8();
+    let (width, height) = image.dimensions();
+
+    if width < 3 || height < 3 {
+        bail!("Nine-patch image is too small to have a border");
+    }
+
+    let is_marker = |x: u32, y: u32| image.get_pixel(x, y).0 == [0, 0, 0, 255];
+
+    let columns = marker_span((1..width - 1).filter(|&x| is_marker(x, 0)))
+        .context("Nine-patch image has no stretch markers in its top row")?;
+    let rows = marker_span((1..height - 1).filter(|&y| is_marker(0, y)))
+        .context("Nine-patch image has no stretch markers in its left column")?;
+
+    let inner: RgbaImage =
+        image::imageops::crop_imm(&image, 1, 1, width - 2, height - 2).to_image();
+
+    let mut writer = Cursor::new(Vec::new());
+    inner.write_to(&mut writer, image::ImageFormat::Png)?;
+
+    // Markers are in border coordinates, which are one pixel off from the image's
+    let center = Rect::new(
+        columns.0 - 1,
+        rows.0 - 1,
+        columns.1 - columns.0 + 1,
+        rows.1 - rows.0 + 1,
+    );
+
+    Ok((writer.into_inner(), center))
+}
+
+/// The first and last marker position
+fn marker_span(mut markers: impl Iterator<Item = u32>) -> Option<(u32, u32)> {
+    let first = markers.next()?;
+    let last = markers.last().unwrap_or(first);
+
+    Some((first, last))
+}
+
+/// The size of an encoded raster image, without decoding all of it
+pub fn image_size(data: &[u8]) -> anyhow::Result<Size> {
+    let (width, height) = image::ImageReader::new(Cursor::new(data))
+        .with_guessed_format()?
+        .into_dimensions()?;
+
+    Ok(Size::new(width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_center_from_insets() {
+        let insets = SliceInsets {
+            left: 4,
+            top: 2,
+            right: 6,
+            bottom: 8,
+        };
+
+        assert_eq!(
+            insets.center(Size::new(32, 32)).unwrap(),
+            Rect::new(4, 2, 22, 22)
+        );
+        assert!(insets.center(Size::new(10, 32)).is_err());
+    }
+
+    #[test]
+    fn test_strip_nine_patch() {
+        let mut image = RgbaImage::from_pixel(12, 10, Rgba([255, 0, 0, 255]));
+        for x in 0..12 {
+            image.put_pixel(x, 0, Rgba([0, 0, 0, 0]));
+        }
+        for y in 0..10 {
+            image.put_pixel(0, y, Rgba([0, 0, 0, 0]));
+        }
+        for x in 4..=7 {
+            image.put_pixel(x, 0, Rgba([0, 0, 0, 255]));
+        }
+        for y in 3..=5 {
+            image.put_pixel(0, y, Rgba([0, 0, 0, 255]));
+        }
+
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+
+        let (stripped, center) = strip_nine_patch(data.get_ref()).unwrap();
+
+        assert_eq!(image_size(&stripped).unwrap(), Size::new(10, 8));
+        assert_eq!(center, Rect::new(3, 2, 4, 3));
+    }
+
+    #[test]
+    fn test_nine_patch_path() {
+        assert_eq!(
+            nine_patch_path(RelativePath::new("ui/button.9.png")).unwrap(),
+            RelativePathBuf::from("ui/button.png")
+        );
+        assert!(nine_patch_path(RelativePath::new("ui/button.png")).is_none());
+    }
+}
@@ -1,22 +1,198 @@
+use anyhow::Context;
+use regex::{Captures, Regex};
 use resvg::{
     tiny_skia::Pixmap,
     usvg::{Options, Transform, Tree, fontdb::Database},
 };
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock},
+};
+
+/// The size an SVG is rasterized at, which is its intrinsic size unless
+/// any of these are set
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SvgRaster {
+    /// The width to render at, keeping the aspect ratio if no height is set
+    pub width: Option<u32>,
+    /// The height to render at, keeping the aspect ratio if no width is set
+    pub height: Option<u32>,
+    /// Multiplies the size, after the width and height are applied
+    pub scale: Option<f32>,
+    /// The DPI lengths in physical units such as `mm` or `pt` are converted
+    /// to pixels with, 96 by default
+    pub dpi: Option<f32>,
+}
+
+impl SvgRaster {
+    pub fn is_intrinsic(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn size(&self, width: f32, height: f32) -> (u32, u32) {
+        let (mut width, mut height) = match (self.width, self.height) {
+            (Some(w), Some(h)) => (w as f32, h as f32),
+            (Some(w), None) => (w as f32, w as f32 * height / width),
+            (None, Some(h)) => (h as f32 * width / height, h as f32),
+            (None, None) => (width, height),
+        };
+
+        if let Some(scale) = self.scale {
+            width *= scale;
+            height *= scale;
+        }
+
+        (width.max(1.0) as u32, height.max(1.0) as u32)
+    }
+}
+
+/// The sidecar file rasterization options are read from, e.g. `icon.svg.raster.json`
+pub fn sidecar_name(file_name: &str) -> String {
+    format!("{file_name}.raster.json")
+}
+
+/// A color set by a paint attribute or style property, e.g. `fill="#fff"`
+/// or `style="stroke: currentColor"`
+static PAINT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)(\b(?:fill|stroke|stop-color|flood-color|lighting-color|color)\s*(?:=\s*["']|:\s*))([^"';>\s]+)"#,
+    )
+    .unwrap()
+});
+
+/// Swap the colors an SVG paints with according to a map of colors, such
+/// as `currentColor` or `#000` to `#ffffff`. Colors are compared without
+/// regard to case, and short hex colors match their long form.
+pub fn recolor(data: &[u8], colors: &BTreeMap<String, String>) -> anyhow::Result<Vec<u8>> {
+    let text = std::str::from_utf8(data).context("SVG is not valid UTF-8")?;
+
+    let colors: BTreeMap<String, &str> = colors
+        .iter()
+        .map(|(from, to)| (normalize_color(from), to.as_str()))
+        .collect();
+
+    let recolored = PAINT.replace_all(text, |captures: &Captures| {
+        match colors.get(&normalize_color(&captures[2])) {
+            Some(to) => format!("{}{to}", &captures[1]),
+            None => captures[0].to_string(),
+        }
+    });
+
+    Ok(recolored.into_owned().into_bytes())
+}
+
+fn normalize_color(color: &str) -> String {
+    let color = color.trim().to_ascii_lowercase();
 
-pub async fn svg_to_png(data: &[u8], fontdb: Arc<Database>) -> anyhow::Result<Vec<u8>> {
-    let opt = Options {
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 3 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            hex.chars().fold(String::from("#"), |mut long, c| {
+                long.push(c);
+                long.push(c);
+                long
+            })
+        }
+        _ => color,
+    }
+}
+
+pub async fn svg_to_png(
+    data: &[u8],
+    fontdb: Arc<Database>,
+    raster: SvgRaster,
+) -> anyhow::Result<Vec<u8>> {
+    let mut opt = Options {
         fontdb,
         ..Default::default()
     };
 
+    if let Some(dpi) = raster.dpi {
+        opt.dpi = dpi;
+    }
+
     let rtree = Tree::from_data(data, &opt)?;
-    let pixmap_size = rtree.size();
+    let intrinsic = rtree.size();
+    let (width, height) = raster.size(intrinsic.width(), intrinsic.height());
 
-    let mut pixmap = Pixmap::new(pixmap_size.width() as u32, pixmap_size.height() as u32).unwrap();
-    resvg::render(&rtree, Transform::identity(), &mut pixmap.as_mut());
+    let transform = Transform::from_scale(
+        width as f32 / intrinsic.width(),
+        height as f32 / intrinsic.height(),
+    );
+
+    let mut pixmap = Pixmap::new(width, height).context("SVG has no size")?;
+    resvg::render(&rtree, transform, &mut pixmap.as_mut());
 
     let encoded = pixmap.encode_png()?;
 
     Ok(encoded)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECT: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="24" height="12" viewBox="0 0 24 12"><rect width="24" height="12" fill="red"/></svg>"#;
+
+    async fn render(raster: SvgRaster) -> image::RgbaImage {
+        let png = svg_to_png(RECT, Arc::new(Database::new()), raster)
+            .await
+            .unwrap();
+
+        image::load_from_memory(&png).unwrap().to_rgba8()
+    }
+
+    #[tokio::test]
+    async fn test_raster_sizes() {
+        let intrinsic = render(SvgRaster::default()).await;
+        assert_eq!(intrinsic.dimensions(), (24, 12));
+
+        let width = render(SvgRaster {
+            width: Some(48),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(width.dimensions(), (48, 24));
+
+        let scaled = render(SvgRaster {
+            height: Some(6),
+            scale: Some(3.0),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(scaled.dimensions(), (36, 18));
+
+        // The drawing is scaled up along with the canvas
+        assert_eq!(scaled.get_pixel(35, 17).0, [255, 0, 0, 255]);
+    }
+
+    #[tokio::test]
+    async fn test_raster_dpi() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="1in" height="0.5in"><rect width="100%" height="100%" fill="red"/></svg>"#;
+        let raster = SvgRaster {
+            dpi: Some(48.0),
+            ..Default::default()
+        };
+
+        let png = svg_to_png(svg, Arc::new(Database::new()), raster)
+            .await
+            .unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (48, 24));
+    }
+
+    #[test]
+    fn test_recolor() {
+        let svg = br##"<svg fill="currentColor"><path fill="#FFF" stroke="#fff8"/><rect style="fill: #ffffff; stroke:Black"/></svg>"##;
+        let colors = BTreeMap::from([
+            ("currentcolor".to_string(), "#111111".to_string()),
+            ("#ffffff".to_string(), "#222222".to_string()),
+            ("black".to_string(), "#333333".to_string()),
+        ]);
+
+        let recolored = String::from_utf8(recolor(svg, &colors).unwrap()).unwrap();
+        assert_eq!(
+            recolored,
+            r##"<svg fill="#111111"><path fill="#222222" stroke="#fff8"/><rect style="fill: #222222; stroke:#333333"/></svg>"##
+        );
+    }
+}
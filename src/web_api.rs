@@ -1,47 +1,77 @@
 use crate::{
     asset::{Asset, AssetType},
     auth::Auth,
-    config::{Creator, CreatorType},
+    config::{Creator, CreatorType, PollOptions, RetryOptions},
 };
 use anyhow::{Context, bail};
-use log::{debug, warn};
+use log::{debug, info, warn};
 use reqwest::{
-    RequestBuilder, Response, StatusCode,
-    header::{self},
+    Method, RequestBuilder, Response, StatusCode,
+    header::{self, HeaderMap},
     multipart,
 };
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 const UPLOAD_URL: &str = "https://apis.roblox.com/assets/v1/assets";
 const OPERATION_URL: &str = "https://apis.roblox.com/assets/v1/operations";
 const ASSET_DESCRIPTION: &str = "Uploaded by Asphalt";
 const MAX_DISPLAY_NAME_LENGTH: usize = 50;
+/// The longest to wait between checks on an operation
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// The longest a retry waits, before jitter
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// The window a rate limit applies to when its header doesn't say
+const DEFAULT_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
 
 pub struct WebApiClient {
     inner: reqwest::Client,
     auth: Auth,
     creator: Creator,
     expected_price: Option<u32>,
+    retry: RetryOptions,
+    polling: PollOptions,
+    /// Uploads and operation polls have separate quotas, shared by every
+    /// request in flight
+    upload_limiter: RateLimiter,
+    operation_limiter: RateLimiter,
 }
 
 impl WebApiClient {
-    pub fn new(auth: Auth, creator: Creator, expected_price: Option<u32>) -> Self {
+    pub fn new(
+        auth: Auth,
+        creator: Creator,
+        expected_price: Option<u32>,
+        retry: RetryOptions,
+        polling: PollOptions,
+    ) -> Self {
         WebApiClient {
             inner: reqwest::Client::new(),
             auth,
             creator,
             expected_price,
+            retry,
+            polling,
+            upload_limiter: RateLimiter::new("Uploads"),
+            operation_limiter: RateLimiter::new("Operation polls"),
         }
     }
 
+    /// Upload an asset and wait for Roblox to process it
     pub async fn upload(&self, asset: &Asset) -> anyhow::Result<u64> {
-        let api_key = self
-            .auth
-            .api_key
-            .clone()
-            .context("An API key is necessary to upload")?;
+        let operation_id = self.create_asset(asset).await?;
 
+        match self.wait_for_operation(&operation_id).await? {
+            Some(asset_id) => Ok(asset_id),
+            None => bail!("Roblox is still processing the upload, operation {operation_id}"),
+        }
+    }
+
+    /// Upload an asset, returning the ID of the operation processing it
+    pub async fn create_asset(&self, asset: &Asset) -> anyhow::Result<String> {
         let file_name = asset.path.file_name().unwrap();
         let display_name = trim_display_name(file_name);
 
@@ -55,13 +85,46 @@ impl WebApiClient {
             description: ASSET_DESCRIPTION,
         };
 
-        let len = asset.data.len() as u64;
         let req_json = serde_json::to_string(&req)?;
-        let mime = req.asset_type.file_type().to_owned();
-        let name = file_name.to_owned();
+        self.send_asset(Method::POST, UPLOAD_URL.to_string(), req_json, asset)
+            .await
+            .context("Failed to upload asset")
+    }
+
+    /// Upload an asset as a new version of an existing one, returning the ID
+    /// of the operation processing it
+    pub async fn update(&self, asset_id: u64, asset: &Asset) -> anyhow::Result<String> {
+        // Only the content changes, so no update mask is needed
+        let req = WebAssetUpdateRequest { asset_id };
+
+        let req_json = serde_json::to_string(&req)?;
+        self.send_asset(
+            Method::PATCH,
+            format!("{UPLOAD_URL}/{asset_id}"),
+            req_json,
+            asset,
+        )
+        .await
+        .with_context(|| format!("Failed to update asset {asset_id}"))
+    }
+
+    /// Send an asset's content along with its request, returning the ID of
+    /// the operation processing it
+    async fn send_asset(
+        &self,
+        method: Method,
+        url: String,
+        req_json: String,
+        asset: &Asset,
+    ) -> anyhow::Result<String> {
+        let api_key = self.api_key()?;
+
+        let len = asset.data.len() as u64;
+        let mime = asset.ty.file_type().to_owned();
+        let name = asset.path.file_name().unwrap().to_owned();
 
         let res = self
-            .send_with_retry(|| {
+            .send_with_retry(&self.upload_limiter, || {
                 let file_part = multipart::Part::stream_with_length(
                     reqwest::Body::from(asset.data.clone()),
                     len,
@@ -75,7 +138,7 @@ impl WebApiClient {
                     .part("fileContent", file_part);
 
                 self.inner
-                    .post(UPLOAD_URL)
+                    .request(method.clone(), &url)
                     .header("x-api-key", &api_key)
                     .multipart(form)
             })
@@ -86,27 +149,26 @@ impl WebApiClient {
 
         if status.is_success() {
             let operation: WebAssetOperation = serde_json::from_str(&body)?;
-
-            match self.poll_operation(operation.operation_id, &api_key).await {
-                Ok(Some(id)) => Ok(id),
-                Ok(None) => bail!("Failed to get asset ID"),
-                Err(e) => Err(e),
-            }
+            Ok(operation.operation_id)
         } else {
-            bail!("Failed to upload asset: {} - {}", status, body)
+            bail!("{} - {}", status, body)
         }
     }
 
-    async fn poll_operation(&self, id: String, api_key: &str) -> anyhow::Result<Option<u64>> {
-        let mut delay = Duration::from_secs(1);
-        const MAX_POLLS: u32 = 10;
+    /// Poll an upload's operation until it's done, giving up with `None` once
+    /// the configured number of polls or time has passed
+    pub async fn wait_for_operation(&self, id: &str) -> anyhow::Result<Option<u64>> {
+        let api_key = self.api_key()?;
+        let started = Instant::now();
+        let timeout = Duration::from_secs(self.polling.timeout);
+        let mut delay = Duration::from_secs_f64(self.polling.interval).min(MAX_POLL_INTERVAL);
 
-        for attempt in 0..MAX_POLLS {
+        for poll in 1..=self.polling.max_polls {
             let res = self
-                .send_with_retry(|| {
+                .send_with_retry(&self.operation_limiter, || {
                     self.inner
                         .get(format!("{OPERATION_URL}/{id}"))
-                        .header("x-api-key", api_key)
+                        .header("x-api-key", &api_key)
                 })
                 .await?;
 
@@ -129,48 +191,313 @@ impl WebApiClient {
 
             debug!("Operation not done yet");
 
-            if attempt < MAX_POLLS - 1 {
-                tokio::time::sleep(delay).await;
-                delay *= 2;
+            if poll == self.polling.max_polls || started.elapsed() + delay > timeout {
+                break;
             }
+
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_POLL_INTERVAL);
         }
 
-        bail!("Operation polling exceeded maximum retries")
+        Ok(None)
     }
 
-    async fn send_with_retry<F>(&self, make_req: F) -> anyhow::Result<Response>
+    fn api_key(&self) -> anyhow::Result<String> {
+        self.auth
+            .api_key
+            .clone()
+            .context("An API key is necessary to upload")
+    }
+
+    /// Send a request, retrying it when it's rate limited, the server has
+    /// a transient error, or the connection fails or times out
+    async fn send_with_retry<F>(
+        &self,
+        limiter: &RateLimiter,
+        make_req: F,
+    ) -> anyhow::Result<Response>
     where
         F: Fn() -> RequestBuilder,
     {
-        const MAX: u8 = 5;
-        let mut attempt = 0;
+        let max_attempts = self.retry.max_attempts;
+        let timeout = Duration::from_secs(self.retry.timeout);
+        let mut attempt = 1;
 
         loop {
-            let res = make_req().send().await?;
+            limiter.acquire().await;
+
+            let res = match make_req().timeout(timeout).send().await {
+                Ok(res) => res,
+                Err(err) if is_transient(&err) && attempt < max_attempts => {
+                    let wait = retry_delay(attempt);
+                    warn!(
+                        "Request failed ({err}), retrying in {:.1} seconds",
+                        wait.as_secs_f64()
+                    );
+
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("Request failed after {attempt} attempt(s)"));
+                }
+            };
+
+            limiter.observe(res.headers());
 
-            match res.status() {
-                StatusCode::TOO_MANY_REQUESTS if attempt < MAX => {
+            let status = res.status();
+            let wait = match status {
+                StatusCode::TOO_MANY_REQUESTS => {
                     let wait = res
                         .headers()
                         .get(header::RETRY_AFTER)
                         .and_then(|h| h.to_str().ok())
                         .and_then(|s| s.parse::<u64>().ok())
                         .map(Duration::from_secs)
-                        .unwrap_or_else(|| Duration::from_secs(1 << attempt));
-
-                    tokio::time::sleep(wait).await;
-                    attempt += 1;
+                        .unwrap_or_else(|| retry_delay(attempt));
 
-                    warn!(
-                        "Rate limited, retrying in {} seconds",
-                        wait.as_millis() / 1000
-                    );
-
-                    continue;
+                    // Hold back every other request too, they'd only be rejected
+                    limiter.pause(wait);
+                    wait
                 }
+                StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT => retry_delay(attempt),
                 _ => return Ok(res),
+            };
+
+            if attempt >= max_attempts {
+                let body = res.text().await.unwrap_or_default();
+                bail!("Request failed after {attempt} attempt(s): {status} - {body}");
             }
+
+            warn!(
+                "Request failed ({status}), retrying in {:.1} seconds",
+                wait.as_secs_f64()
+            );
+
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Whether a request failed in a way that might not happen again, such as a
+/// timeout or a reset connection
+fn is_transient(err: &reqwest::Error) -> bool {
+    if err.is_timeout() || err.is_connect() {
+        return true;
+    }
+
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        let reset = cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|io_err| {
+                matches!(
+                    io_err.kind(),
+                    std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionAborted
+                )
+            });
+
+        if reset {
+            return true;
         }
+        source = cause.source();
+    }
+
+    false
+}
+
+/// How long to wait before retrying after a failed attempt: doubling each
+/// time up to a limit, with jitter so concurrent requests don't retry in step
+fn retry_delay(attempt: u32) -> Duration {
+    let delay = Duration::from_secs(1)
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY);
+
+    delay.mul_f64(rand::random_range(0.5..=1.0))
+}
+
+/// A token bucket that spreads requests out to fit the quota Open Cloud
+/// reports in its rate limit headers. Until a response reports one,
+/// requests aren't held back.
+struct RateLimiter {
+    name: &'static str,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            bucket: Mutex::new(Bucket::new(Instant::now())),
+        }
+    }
+
+    /// Wait until a request can be sent
+    async fn acquire(&self) {
+        loop {
+            let wait = match self.bucket.lock().unwrap().take(Instant::now()) {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn observe(&self, headers: &HeaderMap) {
+        let Some(limit) = RateLimit::from_headers(headers) else {
+            return;
+        };
+
+        if self.bucket.lock().unwrap().observe(limit, Instant::now()) {
+            info!(
+                "{} are limited to {} requests every {} seconds",
+                self.name,
+                limit.limit,
+                limit.window.as_secs()
+            );
+        }
+    }
+
+    fn pause(&self, wait: Duration) {
+        self.bucket.lock().unwrap().pause(Instant::now() + wait);
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens added per second, if a quota is known
+    rate: Option<f64>,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn new(now: Instant) -> Self {
+        Self {
+            rate: None,
+            capacity: 0.0,
+            tokens: 0.0,
+            updated: now,
+            paused_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if let Some(rate) = self.rate {
+            let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(self.capacity);
+        }
+
+        self.updated = now;
+    }
+
+    /// Take a token, or say how long to wait before trying again
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Err(until - now);
+            }
+
+            self.paused_until = None;
+        }
+
+        let Some(rate) = self.rate else {
+            return Ok(());
+        };
+
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+
+    /// Fit the bucket to a reported quota, returning whether its rate changed
+    fn observe(&mut self, limit: RateLimit, now: Instant) -> bool {
+        self.refill(now);
+
+        let rate = limit.limit as f64 / limit.window.as_secs_f64();
+        let changed = self.rate != Some(rate);
+
+        if changed {
+            if self.rate.is_none() {
+                self.tokens = limit.limit as f64;
+            }
+
+            self.rate = Some(rate);
+            self.capacity = limit.limit as f64;
+        }
+
+        if let Some(remaining) = limit.remaining {
+            self.tokens = self.tokens.min(remaining as f64);
+
+            if remaining == 0
+                && let Some(reset) = limit.reset
+            {
+                self.pause(now + reset);
+            }
+        }
+
+        changed
+    }
+
+    fn pause(&mut self, until: Instant) {
+        self.tokens = 0.0;
+        self.paused_until = Some(self.paused_until.map_or(until, |paused| paused.max(until)));
+    }
+}
+
+/// A quota read from the `x-ratelimit-*` headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RateLimit {
+    /// Requests allowed each window
+    limit: u32,
+    window: Duration,
+    remaining: Option<u32>,
+    /// How long until the window resets
+    reset: Option<Duration>,
+}
+
+impl RateLimit {
+    /// The limit header lists the quota first, optionally followed by its
+    /// policies such as `60, 60;w=60`
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok();
+
+        let limit_header = header("x-ratelimit-limit")?;
+        let limit = limit_header.split([',', ';']).next()?.trim().parse().ok()?;
+        if limit == 0 {
+            return None;
+        }
+
+        let window = limit_header
+            .split([',', ';'])
+            .filter_map(|part| part.trim().strip_prefix("w="))
+            .find_map(|seconds| seconds.parse().ok())
+            .filter(|&seconds| seconds > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_RATE_LIMIT_WINDOW);
+
+        Some(Self {
+            limit,
+            window,
+            remaining: header("x-ratelimit-remaining").and_then(|value| value.trim().parse().ok()),
+            reset: header("x-ratelimit-reset")
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs),
+        })
     }
 }
 
@@ -183,6 +510,12 @@ struct WebAssetRequest {
     creation_context: WebAssetRequestCreationContext,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebAssetUpdateRequest {
+    asset_id: u64,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct WebAssetRequestCreationContext {
@@ -245,3 +578,91 @@ fn trim_display_name(name: &str) -> String {
         full_path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for &(name, value) in pairs {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_rate_limit_headers() {
+        let limit = RateLimit::from_headers(&headers(&[
+            ("x-ratelimit-limit", "30, 30;w=10"),
+            ("x-ratelimit-remaining", "4"),
+            ("x-ratelimit-reset", "3"),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            limit,
+            RateLimit {
+                limit: 30,
+                window: Duration::from_secs(10),
+                remaining: Some(4),
+                reset: Some(Duration::from_secs(3)),
+            }
+        );
+
+        let limit = RateLimit::from_headers(&headers(&[("x-ratelimit-limit", "60")])).unwrap();
+        assert_eq!(limit.window, DEFAULT_RATE_LIMIT_WINDOW);
+
+        assert!(RateLimit::from_headers(&headers(&[])).is_none());
+    }
+
+    #[test]
+    fn test_invalid_request_is_not_retried() {
+        let err = reqwest::Client::new().get("not a url").build().unwrap_err();
+
+        assert!(!is_transient(&err));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        for attempt in 1..10 {
+            let delay = retry_delay(attempt);
+            let full = Duration::from_secs(1 << (attempt - 1)).min(MAX_RETRY_DELAY);
+
+            assert!(delay >= full / 2 && delay <= full, "{attempt}: {delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_bucket_throttles_to_quota() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(now);
+        assert!(bucket.take(now).is_ok());
+
+        let limit = RateLimit {
+            limit: 10,
+            window: Duration::from_secs(10),
+            remaining: Some(1),
+            reset: None,
+        };
+        assert!(bucket.observe(limit, now));
+        assert!(!bucket.observe(limit, now));
+
+        assert!(bucket.take(now).is_ok());
+        assert_eq!(bucket.take(now), Err(Duration::from_secs(1)));
+        assert!(bucket.take(now + Duration::from_secs(1)).is_ok());
+
+        // Running out pauses every request until the window resets
+        let exhausted = RateLimit {
+            remaining: Some(0),
+            reset: Some(Duration::from_secs(5)),
+            ..limit
+        };
+        bucket.observe(exhausted, now + Duration::from_secs(1));
+        assert_eq!(
+            bucket.take(now + Duration::from_secs(2)),
+            Err(Duration::from_secs(4))
+        );
+    }
+}